@@ -0,0 +1,274 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides static export of a site using the same [`Engine`] that serves it.
+//!
+//! Each route served by [`crate::routes::router`] is written to the output directory such that
+//! `/{route}` maps to `{out}/{route}/index.html`, with feeds and assets written at their exact paths.
+
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, trace};
+
+use super::common;
+use super::render::{Engine, Pagination};
+use super::{anyhow, Context, Result};
+
+/// Exports every page, post, feed, and asset served for a site into `out`.
+pub(crate) async fn export<P: AsRef<Path>>(engine: &Engine, out: P) -> Result<()> {
+    let out = out.as_ref();
+    info!("Exporting site to '{}'", out.display());
+    let webroot = Path::new(&engine.app.docpaths.webroot);
+    tokio::fs::create_dir_all(out)
+        .await
+        .with_context(|| format!("failed to create '{}'", out.display()))?;
+    if out.canonicalize()?.starts_with(webroot.canonicalize()?) {
+        return Err(anyhow!(
+            "export directory '{}' must not be within the webroot",
+            out.display()
+        ));
+    }
+
+    let mut topics = vec!["main".to_owned()];
+    topics.extend(engine.topic_slugs.iter().cloned());
+    for topic_slug in &topics {
+        export_topic(engine, topic_slug, out).await?;
+    }
+
+    info!("Exporting archive");
+    for (route, output) in engine.render_archive_pages().await? {
+        write_file(route_to_file(out, &route), output).await?;
+    }
+
+    info!("Exporting tags");
+    write_file(route_to_file(out, "/tags"), engine.render_tags().await?).await?;
+    for tag in engine.tags().await? {
+        if let Some(output) = engine.render_tag(&tag.slug).await? {
+            let route = format!("/tags/{}", tag.slug);
+            write_file(route_to_file(out, &route), output).await?;
+        }
+        if let Some(rss) = engine.tag_rss(&tag.slug).await? {
+            write_file(out.join("tags").join(&tag.slug).join("rss.xml"), rss).await?;
+        }
+    }
+
+    info!("Exporting RSS feed");
+    write_file(out.join("rss.xml"), engine.rss().await?).await?;
+    info!("Exporting Atom feed");
+    write_file(out.join("atom.xml"), engine.atom().await?).await?;
+    info!("Exporting JSON Feed");
+    write_file(out.join("feed.json"), engine.json_feed().await?).await?;
+    info!("Exporting sitemap");
+    write_file(out.join("sitemap.xml"), engine.sitemap().await?).await?;
+    let mut part = 1;
+    while let Some(output) = engine.sitemap_part(part).await? {
+        write_file(out.join("sitemap").join(format!("{}.xml", part)), output).await?;
+        part += 1;
+    }
+
+    write_file(out.join("robots.txt"), engine.robots()).await?;
+
+    info!("Copying static assets");
+    let static_path = webroot.join("static");
+    copy_tree(static_path.clone(), out.join("static")).await?;
+    let favicon = static_path.join("favicon.ico");
+    if favicon.exists() {
+        tokio::fs::copy(&favicon, out.join("favicon.ico"))
+            .await
+            .with_context(|| format!("failed to copy '{}'", favicon.display()))?;
+    }
+
+    copy_tree(webroot.join(".well-known"), out.join(".well-known")).await?;
+
+    for topic_slug in &engine.topic_slugs {
+        debug!("Copying assets for topic: '{}'", topic_slug);
+        let ext = webroot.join(topic_slug).join("ext");
+        copy_tree(ext, out.join(topic_slug).join("ext")).await?;
+    }
+
+    info!("Site exported to '{}'", out.display());
+    Ok(())
+}
+
+/// Writes each page of a topic, and each of its posts.
+async fn export_topic(engine: &Engine, topic_slug: &str, out: &Path) -> Result<()> {
+    info!("Exporting topic: '{}'", topic_slug);
+    let mut page = 1;
+    while let Some(output) = engine.render_topic(topic_slug, page).await? {
+        let dest = route_to_file(out, &Pagination::url(topic_slug, page));
+        write_file(dest, output).await?;
+        page += 1;
+    }
+
+    if let Some(rss) = engine.topic_rss(topic_slug).await? {
+        write_file(out.join(topic_slug).join("rss.xml"), rss).await?;
+    }
+
+    let posts_path = Path::new(&engine.app.docpaths.webroot)
+        .join(topic_slug)
+        .join("posts");
+    if !posts_path.exists() {
+        debug!("No posts to export for topic: '{}'", topic_slug);
+        return Ok(());
+    }
+
+    for post in engine.load_topic(topic_slug).await? {
+        let output = engine.render_post(topic_slug, &post.slug).await?;
+        write_file(route_to_file(out, &post.url), output).await?;
+    }
+
+    Ok(())
+}
+
+/// Maps an HTML route to the `index.html` that serves it from a static file server.
+fn route_to_file(out: &Path, route: &str) -> PathBuf {
+    out.join(route.trim_start_matches('/')).join("index.html")
+}
+
+async fn write_file(dest: PathBuf, content: String) -> Result<()> {
+    trace!("Writing '{}'", dest.display());
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("failed to create '{}'", parent.display()))?;
+    }
+
+    tokio::fs::write(&dest, content)
+        .await
+        .with_context(|| format!("failed to write '{}'", dest.display()))
+}
+
+async fn copy_tree(src: PathBuf, dest: PathBuf) -> Result<()> {
+    if !src.exists() {
+        debug!("Skipping missing directory: '{}'", src.display());
+        return Ok(());
+    }
+
+    tokio::task::spawn_blocking(move || common::copy_dir_all(&src, &dest)).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::render::SharedEngine;
+    use crate::testing;
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    /// Lists every file beneath `dir`, relative to it and with `/` separators.
+    fn exported_files(dir: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(next) = dirs.pop() {
+            for entry in std::fs::read_dir(next).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    let relative = path.strip_prefix(dir).unwrap();
+                    files.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        files
+    }
+
+    #[tokio::test]
+    async fn check_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One", "Gallery"]);
+        config.site.posts_per_page = Some(1);
+
+        let files = [
+            ("site/webroot/main/posts/index.md", "Main Page\n"),
+            (
+                "site/webroot/one/posts/1.md",
+                "+++\ntags = [\"First\"]\n+++\nFirst post\n",
+            ),
+            ("site/webroot/one/posts/2024-05-01-2.md", "Second post\n"),
+            ("site/webroot/one/ext/nested/asset.txt", "Topic asset\n"),
+            ("site/webroot/gallery/ext/0.jpg", "some bytes"),
+            ("site/webroot/static/style.css", "body {}\n"),
+            ("site/webroot/static/favicon.ico", "Favicon File\n"),
+            ("site/webroot/.well-known/security.txt", "Contact: me\n"),
+        ];
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut f = File::create(path).unwrap();
+            f.write_all(content.as_bytes()).unwrap();
+        }
+
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        let engine = Engine::new(config).unwrap();
+        export(&engine, out.path()).await.unwrap();
+
+        // Every exported file must match, byte for byte, what a separately loaded server returns.
+        let served = Engine::new(AppConfig::from_path(&config_path).unwrap()).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = crate::routes::router(SharedEngine::new(served));
+        let server = tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = reqwest::Client::new();
+        let mut files = exported_files(out.path());
+        files.sort();
+        for file in &files {
+            let route = match file.strip_suffix("index.html") {
+                Some(dir) => format!("/{}", dir.trim_end_matches('/')),
+                None => format!("/{}", file),
+            };
+            let resp = client
+                .get(format!("http://{}{}", addr, route))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::OK, "{}", route);
+            let body = resp.bytes().await.unwrap();
+            let exported = std::fs::read(out.path().join(file)).unwrap();
+            assert_eq!(body.as_ref(), exported.as_slice(), "{}", file);
+        }
+        server.abort();
+
+        for file in [
+            "index.html",
+            "one/index.html",
+            "one/page/2/index.html",
+            "one/posts/1/index.html",
+            "main/posts/index/index.html",
+            "gallery/index.html",
+            "rss.xml",
+            "atom.xml",
+            "feed.json",
+            "sitemap.xml",
+            "robots.txt",
+            "one/rss.xml",
+            "one/ext/nested/asset.txt",
+            "gallery/ext/0.jpg",
+            "static/style.css",
+            "favicon.ico",
+            ".well-known/security.txt",
+            "tags/index.html",
+            "tags/first/index.html",
+            "tags/first/rss.xml",
+            "archive/index.html",
+            "archive/2024/index.html",
+            "archive/2024/05/index.html",
+        ] {
+            assert!(files.iter().any(|f| f == file), "{} was not exported", file);
+        }
+        assert!(!out.path().join("sitemap").exists());
+        assert!(!out.path().join("main/rss.xml").exists());
+        assert!(!out.path().join("one/page/3").exists());
+    }
+}
@@ -0,0 +1,446 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides validation of a site's configuration TOML and the files it refers to.
+//!
+//! Every problem found is reported as a [`Diagnostic`], located by line and column in the TOML
+//! where possible, so a site can be fixed in one pass rather than one error at a time.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info};
+use serde::Deserialize;
+use toml::Spanned;
+
+use super::common;
+use super::config::AppConfig;
+use super::render::Engine;
+use super::{anyhow, Result};
+
+/// A position in the configuration TOML, counted from `1`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Finds the [`Location`] of the byte `offset` in `src`.
+    fn from_offset(src: &str, offset: usize) -> Location {
+        let before = &src[..offset.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .map_or(0, |line| line.chars().count())
+            + 1;
+        Location { line, column }
+    }
+}
+
+/// A single problem with a site's configuration.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(message: String) -> Diagnostic {
+        Diagnostic {
+            location: None,
+            message,
+        }
+    }
+
+    fn at(src: &str, span: Option<Range<usize>>, message: String) -> Diagnostic {
+        Diagnostic {
+            location: span.map(|span| Location::from_offset(src, span.start)),
+            message,
+        }
+    }
+}
+
+/// The outcome of checking a configuration file.
+#[derive(Debug)]
+pub(crate) struct Report {
+    pub path: PathBuf,
+    pub app: Option<AppConfig>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            match diagnostic.location {
+                Some(Location { line, column }) => writeln!(
+                    f,
+                    "{}:{}:{}: {}",
+                    self.path.display(),
+                    line,
+                    column,
+                    diagnostic.message
+                )?,
+                None => writeln!(f, "{}: {}", self.path.display(), diagnostic.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spans of the configuration values that refer to something outside the TOML.
+///
+/// Deserialized alongside [`AppConfig`] only to locate problems found after parsing.
+#[derive(Deserialize)]
+struct Spans {
+    site: SiteSpans,
+    server: ServerSpans,
+    docpaths: DocPathSpans,
+    mime_types: HashMap<String, Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+struct SiteSpans {
+    template: Spanned<String>,
+    topics: Vec<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+struct ServerSpans {
+    socket_mode: Option<Spanned<u32>>,
+    tls: Option<TlsSpans>,
+}
+
+#[derive(Deserialize)]
+struct TlsSpans {
+    cert: Spanned<String>,
+    key: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+struct DocPathSpans {
+    templates: Spanned<String>,
+    webroot: Spanned<String>,
+}
+
+/// Checks the configuration at `path`, collecting every problem found.
+pub(crate) fn check<P: AsRef<Path>>(path: P) -> Report {
+    let path = path.as_ref().to_path_buf();
+    debug!("Checking site configuration at '{}'", path.display());
+    let mut report = Report {
+        path,
+        app: None,
+        diagnostics: Vec::new(),
+    };
+
+    let src = match std::fs::read_to_string(&report.path) {
+        Ok(src) => src,
+        Err(err) => {
+            report
+                .diagnostics
+                .push(Diagnostic::new(format!("failed to read file: {}", err)));
+            return report;
+        }
+    };
+
+    let (app, spans) = match (
+        toml::from_str::<AppConfig>(&src),
+        toml::from_str::<Spans>(&src),
+    ) {
+        (Ok(app), Ok(spans)) => (app, spans),
+        (Err(err), _) | (_, Err(err)) => {
+            let message = err.message().trim_end().to_owned();
+            report
+                .diagnostics
+                .push(Diagnostic::at(&src, err.span(), message));
+            return report;
+        }
+    };
+
+    report.diagnostics = check_app(&app, &spans, &src);
+    report.app = Some(app);
+    report
+}
+
+fn check_app(app: &AppConfig, spans: &Spans, src: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let at = |span: Range<usize>, message: String| Diagnostic::at(src, Some(span), message);
+
+    let templates = &spans.docpaths.templates;
+    if !Path::new(&app.docpaths.templates).is_dir() {
+        diagnostics.push(at(
+            templates.span(),
+            format!(
+                "templates directory '{}' does not exist",
+                templates.get_ref()
+            ),
+        ));
+    }
+    let webroot = &spans.docpaths.webroot;
+    let webroot_exists = Path::new(&app.docpaths.webroot).is_dir();
+    if !webroot_exists {
+        diagnostics.push(at(
+            webroot.span(),
+            format!("webroot directory '{}' does not exist", webroot.get_ref()),
+        ));
+    }
+
+    let template = &spans.site.template;
+    let template_path = Path::new(&app.docpaths.templates).join(template.get_ref());
+    if template.get_ref() != "default.tmpl" && !template_path.is_file() {
+        diagnostics.push(at(
+            template.span(),
+            format!("template '{}' does not exist", template_path.display()),
+        ));
+    } else if let Err(err) = Engine::load_template(app) {
+        diagnostics.push(at(
+            template.span(),
+            format!(
+                "template '{}' failed to compile: {:#}",
+                template.get_ref(),
+                err
+            ),
+        ));
+    }
+
+    let mut slugs: HashMap<String, &str> = HashMap::from([("main".to_owned(), "main")]);
+    let mut topic_dirs: Vec<(&str, String, Option<Range<usize>>)> =
+        vec![("main", "main".to_owned(), None)];
+    for topic in &spans.site.topics {
+        let slug = common::slugify(topic.get_ref());
+        if slug.is_empty() {
+            diagnostics.push(at(
+                topic.span(),
+                format!("topic '{}' has an empty slug", topic.get_ref()),
+            ));
+        } else if let Some(existing) = slugs.get(&slug) {
+            diagnostics.push(at(
+                topic.span(),
+                format!(
+                    "topic '{}' has the slug '{}', which is already used by '{}'",
+                    topic.get_ref(),
+                    slug,
+                    existing
+                ),
+            ));
+        } else if common::is_reserved_slug(&slug) {
+            diagnostics.push(at(
+                topic.span(),
+                format!(
+                    "topic '{}' has the slug '{}', which is reserved for the site's own '/{}' route",
+                    topic.get_ref(),
+                    slug,
+                    slug
+                ),
+            ));
+        } else {
+            slugs.insert(slug.clone(), topic.get_ref());
+            topic_dirs.push((topic.get_ref(), slug, Some(topic.span())));
+        }
+    }
+
+    if webroot_exists {
+        for (topic, slug, span) in topic_dirs {
+            for dir in ["posts", "ext"] {
+                let path = Path::new(&app.docpaths.webroot).join(&slug).join(dir);
+                if !path.is_dir() {
+                    diagnostics.push(Diagnostic::at(
+                        src,
+                        span.clone(),
+                        format!("topic '{}' is missing '{}'", topic, path.display()),
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(mode) = &spans.server.socket_mode
+        && *mode.get_ref() > 0o7777
+    {
+        diagnostics.push(at(
+            mode.span(),
+            format!(
+                "socket mode {:o} is not a valid permission mode",
+                mode.get_ref()
+            ),
+        ));
+    }
+
+    if let Some(tls) = &spans.server.tls {
+        for (kind, path) in [("certificate", &tls.cert), ("key", &tls.key)] {
+            if !Path::new(path.get_ref()).is_file() {
+                diagnostics.push(at(
+                    path.span(),
+                    format!("TLS {} '{}' does not exist", kind, path.get_ref()),
+                ));
+            }
+        }
+    }
+
+    let mut mime_types: Vec<_> = spans.mime_types.iter().collect();
+    mime_types.sort_by_key(|(_, value)| value.span().start);
+    for (ext, value) in mime_types {
+        if let Err(err) = value.get_ref().parse::<mime::Mime>() {
+            diagnostics.push(at(
+                value.span(),
+                format!(
+                    "MIME type '{}' for '{}' is invalid: {}",
+                    value.get_ref(),
+                    ext,
+                    err
+                ),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Loads the [`AppConfig`] at `path`, failing with every problem found if it is invalid.
+pub(crate) fn load<P: AsRef<Path>>(path: P) -> Result<AppConfig> {
+    let report = check(path);
+    match report.app {
+        Some(app) if report.diagnostics.is_empty() => {
+            info!("Configuration at '{}' is valid", report.path.display());
+            Ok(app)
+        }
+        _ => {
+            for line in report.to_string().lines() {
+                error!("{}", line);
+            }
+            Err(anyhow!(
+                "found {} problem(s) in '{}'",
+                report.diagnostics.len(),
+                report.path.display()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn locate_offsets() {
+        let src = "one\ntwo\nthree";
+        assert_eq!(
+            Location::from_offset(src, 0),
+            Location { line: 1, column: 1 }
+        );
+        assert_eq!(
+            Location::from_offset(src, 6),
+            Location { line: 2, column: 3 }
+        );
+        assert_eq!(
+            Location::from_offset(src, 8),
+            Location { line: 3, column: 1 }
+        );
+    }
+
+    #[test]
+    fn check_valid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        testing::site(&dir, &["One", "Two"]);
+        let report = check(dir.path().join("config.toml"));
+        assert_eq!(report.diagnostics, Vec::new());
+        assert!(load(dir.path().join("config.toml")).is_ok());
+    }
+
+    #[test]
+    fn check_invalid_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[site]\nname = \"Site\"\nposts_per_page = \"ten\"\n").unwrap();
+        let report = check(&path);
+        assert!(report.app.is_none());
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(
+            report.diagnostics[0].location,
+            Some(Location {
+                line: 3,
+                column: 18
+            })
+        );
+
+        testing::site(&dir, &["One"]);
+        let mut src = std::fs::read_to_string(&path).unwrap();
+        src.push_str("\n[[cache.overrides]]\npath = \"/static/[a\"\n");
+        std::fs::write(&path, src).unwrap();
+        let report = check(&path);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert!(report.diagnostics[0]
+            .message
+            .starts_with("cache override path '/static/[a' is not a valid glob"));
+        assert!(report.diagnostics[0].location.is_some());
+    }
+
+    #[test]
+    fn check_site_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One"]);
+        config.site.topics.push("and more".to_owned());
+        config.site.topics.push("And More".to_owned());
+        config.site.topics.push("Archive".to_owned());
+        config.site.template = "missing.tmpl".to_owned();
+        config
+            .mime_types
+            .insert("bad".to_owned(), "not a mime".to_owned());
+        let key = dir.path().join("privkey.pem");
+        std::fs::write(&key, "").unwrap();
+        config.server.tls = Some(crate::config::Tls {
+            cert: dir.path().join("missing.pem").display().to_string(),
+            key: key.display().to_string(),
+            redirect_port: None,
+        });
+        config.server.socket_mode = Some(0o10660);
+        std::fs::remove_dir_all(dir.path().join("site/webroot/one/ext")).unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+        let report = check(&path);
+        let messages: Vec<&str> = report
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(report.diagnostics.len(), 9, "{:#?}", messages);
+        assert!(messages[0].starts_with("template '") && messages[0].ends_with("does not exist"));
+        assert_eq!(
+            messages[1],
+            "topic 'And More' has the slug 'and-more', which is already used by 'and more'"
+        );
+        assert_eq!(
+            messages[2],
+            "topic 'Archive' has the slug 'archive', which is reserved for the site's own '/archive' route"
+        );
+        assert!(messages[3].starts_with("topic 'One' is missing '"));
+        assert!(messages[4].starts_with("topic 'and more' is missing '"));
+        assert!(messages[5].starts_with("topic 'and more' is missing '"));
+        assert_eq!(
+            messages[6],
+            "socket mode 10660 is not a valid permission mode"
+        );
+        assert!(
+            messages[7].starts_with("TLS certificate '") && messages[7].ends_with("does not exist")
+        );
+        assert!(messages[8].starts_with("MIME type 'not a mime' for 'bad' is invalid"));
+        assert!(report.diagnostics.iter().all(|d| d.location.is_some()));
+        assert!(load(&path).is_err());
+
+        std::fs::write(dir.path().join("site/templates/missing.tmpl"), "{% if %}").unwrap();
+        let report = check(&path);
+        assert!(report.diagnostics[0].message.contains("failed to compile"));
+    }
+}
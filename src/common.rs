@@ -32,6 +32,7 @@ pub(crate) fn str_to_ro_file<P: AsRef<Path>>(content: &str, dest: P) -> Result<(
     let mut options = OpenOptions::new();
     options.create(true);
     options.write(true);
+    options.truncate(true);
     options.mode(0o600);
 
     trace!("Opening '{}' to write", &dest.as_ref().display());
@@ -77,6 +78,28 @@ pub(crate) fn str_to_ro_file<P: AsRef<Path>>(content: &str, dest: P) -> Result<(
     Ok(())
 }
 
+/// Replaces `dest` with `content` as [`str_to_ro_file`] would, without ever leaving it half-written.
+///
+/// The content is written to a hidden temporary file next to `dest`, which is then renamed over it.
+pub(crate) fn replace_ro_file<P: AsRef<Path>>(content: &str, dest: P) -> Result<()> {
+    let dest = dest.as_ref();
+    let name = dest
+        .file_name()
+        .ok_or_else(|| anyhow!("'{}' is not a file path", dest.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(".tmp");
+    let tmp = dest.with_file_name(tmp_name);
+    str_to_ro_file(content, &tmp)?;
+    std::fs::rename(&tmp, dest).with_context(|| {
+        format!(
+            "failed to move '{}' into place at '{}'",
+            tmp.display(),
+            dest.display()
+        )
+    })
+}
+
 /// Returns a `Result<Vec<Pathbuf>>` for a given pattern.
 ///
 /// The returned items are reverse-lexically sorted.
@@ -105,6 +128,73 @@ pub fn path_matches(pat: &str) -> Result<Vec<PathBuf>> {
     }
 }
 
+/// Recursively copies the contents of `src` into `dest`, creating directories as needed.
+pub(crate) fn copy_dir_all<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dest: Q) -> Result<()> {
+    debug!(
+        "Copying '{}' to '{}'",
+        &src.as_ref().display(),
+        &dest.as_ref().display()
+    );
+    std::fs::create_dir_all(&dest)
+        .with_context(|| format!("failed to create '{}'", &dest.as_ref().display()))?;
+    let entries = std::fs::read_dir(&src)
+        .with_context(|| format!("failed to read '{}'", &src.as_ref().display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let target = dest.as_ref().join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(entry.path(), &target)?;
+        } else {
+            trace!("Copying '{}'", &entry.path().display());
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy '{}'", &entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Slugs taken by the engine's own routes and `webroot` directories, which no topic may use.
+pub(crate) const RESERVED_SLUGS: &[&str] = &[
+    "main",
+    "static",
+    "page",
+    "tags",
+    "archive",
+    "search",
+    "search.json",
+    "sitemap",
+    "sitemap.xml",
+    "rss.xml",
+    "atom.xml",
+    "feed.json",
+    "favicon.ico",
+    "robots.txt",
+    ".well-known",
+];
+
+/// Returns whether `slug` is one of the [`RESERVED_SLUGS`].
+pub(crate) fn is_reserved_slug(slug: &str) -> bool {
+    RESERVED_SLUGS.contains(&slug)
+}
+
+/// Percent-encodes `segment` for use as a single segment of a URL path.
+///
+/// Only the unreserved characters of RFC 3986 are left as they are.
+pub(crate) fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
 /// Returns the slugified topic as a `String`
 pub fn slugify(topic: &str) -> String {
     debug!("Creating slugified topic string from {}", &topic);
@@ -122,4 +212,11 @@ mod tests {
         let paths = path_matches(&pat);
         assert!(paths.is_err());
     }
+
+    #[test]
+    fn encode_path_segments() {
+        assert_eq!(encode_path_segment("web-dev"), "web-dev");
+        assert_eq!(encode_path_segment("c++"), "c%2B%2B");
+        assert_eq!(encode_path_segment("node.js/ü"), "node.js%2F%C3%BC");
+    }
 }
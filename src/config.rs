@@ -17,17 +17,20 @@ copied, modified, or distributed except according to those terms.
 //! - Generating a new application configuration and directory structure (when `arse new` is called)
 
 use std::collections::HashMap;
-use std::fs::create_dir_all;
-use std::path::Path;
-use std::{io::BufRead, usize};
+use std::fmt;
+use std::fs::{create_dir_all, remove_dir_all};
+use std::io::{BufRead, IsTerminal, StdinLock};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 use clap::{crate_authors, crate_description, crate_version, Arg, ArgAction, ArgMatches, Command};
-use log::{debug, error, info, trace};
+use glob::{MatchOptions, Pattern};
+use log::{debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
 use simplelog::{ConfigBuilder, SimpleLogger};
 
-use super::common;
 use super::{anyhow, Context, Result};
+use super::{check, common};
 
 fn args() -> Command {
     Command::new("A Rust Site Engine")
@@ -42,26 +45,233 @@ fn args() -> Command {
                 .help("Sets the log level. Default: INFO. -v = DEBUG, -vv = TRACE"),
         )
         .subcommand(
-            Command::new("run").about("Run the site server").arg(
-                Arg::new("config")
-                    .help("Provides the path to the server configuration file.")
-                    .action(ArgAction::Set)
-                    .required(true)
-                    .value_name("CONFIG")
-                    .index(1),
-            ),
+            Command::new("run")
+                .about("Run the site server")
+                .arg(config_arg())
+                .arg(
+                    Arg::new("drafts")
+                        .long("drafts")
+                        .help("Shows drafts and scheduled posts, marked as DRAFT.")
+                        .action(ArgAction::SetTrue),
+                ),
         )
         .subcommand(
-            Command::new("new").about(
-                "Generates a base directory structure and configuration file for a new site",
-            ),
+            Command::new("build")
+                .about("Export the site as static files")
+                .arg(config_arg())
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .short('o')
+                        .help("Directory the exported site is written to.")
+                        .action(ArgAction::Set)
+                        .required(true)
+                        .value_name("DIR"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Validates a site's configuration and the files it refers to")
+                .arg(config_arg()),
+        )
+        .subcommand(
+            Command::new("topic")
+                .about("Add or remove site topics")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Adds a topic to the configuration and creates its directories")
+                        .arg(config_arg())
+                        .arg(topic_arg())
+                        .arg(dry_run_arg()),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Removes a topic from the configuration")
+                        .arg(config_arg())
+                        .arg(topic_arg())
+                        .arg(
+                            Arg::new("purge")
+                                .long("purge")
+                                .help("Also deletes the topic's posts and ext directories.")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(dry_run_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Generates a base directory structure and configuration file for a new site")
+                .after_help(
+                    "Values not given by flags or a seed file are prompted for when stdin is a terminal.",
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .help("Reads values for the new site from a seed TOML file. Flags take precedence.")
+                        .action(ArgAction::Set)
+                        .value_name("SEED"),
+                )
+                .arg(new_arg("name", "NAME", "Name of the site."))
+                .arg(new_arg("author", "AUTHOR", "Name of the site's author."))
+                .arg(new_arg("url", "URL", "Base URL of the site."))
+                .arg(new_arg("topics", "TOPICS", "Comma-separated site topics."))
+                .arg(new_arg("bind", "BIND", "Address the server binds to. Default: 0.0.0.0"))
+                .arg(
+                    new_arg("port", "PORT", "Port the server listens on. Default: 9090")
+                        .value_parser(clap::value_parser!(u16)),
+                )
+                .arg(new_arg("template", "TEMPLATE", "Tera template used to render the site. Default: default.tmpl"))
+                .arg(
+                    new_arg("dir", "DIR", "Directory the site is created in. Default: the current directory")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
         )
 }
 
+fn new_arg(id: &'static str, value_name: &'static str, help: &'static str) -> Arg {
+    Arg::new(id)
+        .long(id)
+        .help(help)
+        .action(ArgAction::Set)
+        .value_name(value_name)
+}
+
+fn config_arg() -> Arg {
+    Arg::new("config")
+        .help("Provides the path to the server configuration file.")
+        .action(ArgAction::Set)
+        .required(true)
+        .value_name("CONFIG")
+        .index(1)
+}
+
+fn topic_arg() -> Arg {
+    Arg::new("name")
+        .help("Name of the topic.")
+        .action(ArgAction::Set)
+        .required(true)
+        .value_name("NAME")
+        .index(2)
+}
+
+fn dry_run_arg() -> Arg {
+    Arg::new("dry-run")
+        .long("dry-run")
+        .help("Reports the changes that would be made without making them.")
+        .action(ArgAction::SetTrue)
+}
+
+/// The subcommand requested on the command line, along with its loaded [`AppConfig`].
+#[derive(Debug)]
+pub(crate) enum Mode {
+    /// Serve the site, reloading its configuration from `path` on changes: `arse run`
+    Run {
+        app: AppConfig,
+        path: PathBuf,
+        drafts: bool,
+    },
+    /// Export the site as static files to `out`: `arse build`
+    Build { app: AppConfig, out: PathBuf },
+    /// Validate a site's configuration: `arse check`
+    Check { path: PathBuf },
+    /// Add or remove a topic: `arse topic`
+    Topic(TopicCommand),
+    /// Generate a new site: `arse new`
+    New(Seed),
+}
+
+/// Changes made by `arse topic`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum TopicAction {
+    Add,
+    Remove { purge: bool },
+}
+
+/// A topic change to apply to the configuration at `path`.
+#[derive(Debug)]
+pub(crate) struct TopicCommand {
+    pub path: PathBuf,
+    pub name: String,
+    pub action: TopicAction,
+    pub dry_run: bool,
+}
+
+impl TopicCommand {
+    /// Updates the configuration file and site directories, or only reports the changes when `dry_run` is set.
+    ///
+    /// The configuration is written before any directory is created or deleted, so that a
+    /// failure part way through never leaves it naming a topic whose content is gone.
+    pub(crate) fn apply(&self) -> Result<()> {
+        let mut app = AppConfig::from_path(&self.path)?;
+        let slug = common::slugify(&self.name);
+        let topic_path = Path::new(&app.docpaths.webroot).join(&slug);
+        let prefix = if self.dry_run { "[dry-run] " } else { "" };
+
+        match self.action {
+            TopicAction::Add => {
+                app.add_topic(&self.name)?;
+                info!(
+                    "{}Adding topic '{}' to '{}'",
+                    prefix,
+                    &self.name,
+                    self.path.display()
+                );
+            }
+            TopicAction::Remove { purge } => {
+                if purge && common::is_reserved_slug(&slug) {
+                    return Err(anyhow!(
+                        "refusing to delete '{}', which belongs to the site itself",
+                        topic_path.display()
+                    ));
+                }
+                let name = app.remove_topic(&self.name)?;
+                info!(
+                    "{}Removing topic '{}' from '{}'",
+                    prefix,
+                    name,
+                    self.path.display()
+                );
+            }
+        }
+
+        if !self.dry_run {
+            app.write_to(&self.path)
+                .context("failed to write site config to disk")?;
+        }
+
+        match self.action {
+            TopicAction::Add => {
+                info!(
+                    "{}Creating '{}/{{ext,posts}}'",
+                    prefix,
+                    topic_path.display()
+                );
+                if !self.dry_run {
+                    app.create_topic_paths(&slug)?;
+                }
+            }
+            TopicAction::Remove { purge: true } => {
+                info!("{}Deleting '{}'", prefix, topic_path.display());
+                if !self.dry_run && topic_path.exists() {
+                    remove_dir_all(&topic_path)
+                        .with_context(|| format!("failed to delete '{}'", topic_path.display()))?;
+                }
+            }
+            TopicAction::Remove { purge: false } => info!(
+                "{}Leaving '{}' in place, use --purge to delete it",
+                prefix,
+                topic_path.display()
+            ),
+        }
+        Ok(())
+    }
+}
+
 /// Processes command-line arguments and configures logging.
 ///
-/// Returns: [`Result<AppConfig>`] or exits after generating a new config, and writing it to disk.
-pub(crate) fn load() -> Result<AppConfig> {
+/// Returns: [`Result<Mode>`]
+pub(crate) fn load() -> Result<Mode> {
     let matches = args().get_matches();
 
     // Create a Config with ISO timestamps
@@ -80,19 +290,26 @@ pub(crate) fn load() -> Result<AppConfig> {
     info!("Logging started");
 
     debug!("Processing subcommands");
-    let config: Result<AppConfig> = match matches.subcommand() {
+    let mode: Result<Mode> = match matches.subcommand() {
         Some(("run", run_m)) => {
             trace!("Application called with `run` subcommand - loading config from disk");
             runner_config(run_m)
         }
-        Some(("new", _)) => {
-            trace!("Application called with `new` subcommand - creating config from user input");
-            let reader = std::io::stdin();
-            let mut reader = reader.lock();
-            let current_path =
-                std::env::current_dir().context("failed to get current working directory")?;
-            let _ = AppConfig::generate(current_path, &mut reader);
-            std::process::exit(0);
+        Some(("build", build_m)) => {
+            trace!("Application called with `build` subcommand - loading config from disk");
+            build_config(build_m)
+        }
+        Some(("check", check_m)) => {
+            trace!("Application called with `check` subcommand - validating config on disk");
+            check_config(check_m)
+        }
+        Some(("topic", topic_m)) => {
+            trace!("Application called with `topic` subcommand - updating config on disk");
+            topic_command(topic_m)
+        }
+        Some(("new", new_m)) => {
+            trace!("Application called with `new` subcommand - creating config from flags and user input");
+            new_seed(new_m).map(Mode::New)
         }
         _ => {
             let msg = "Unable to load configuration".to_owned();
@@ -101,12 +318,15 @@ pub(crate) fn load() -> Result<AppConfig> {
         }
     };
 
-    config
+    mode
 }
 
-fn runner_config(m: &ArgMatches) -> Result<AppConfig> {
+fn runner_config(m: &ArgMatches) -> Result<Mode> {
     if let Some(value) = m.get_one::<String>("config") {
-        AppConfig::from_path(value)
+        let app = check::load(value)?;
+        let path = PathBuf::from(value);
+        let drafts = m.get_flag("drafts");
+        Ok(Mode::Run { app, path, drafts })
     } else {
         let msg = "Failed to read arguments for 'run' subcommand".to_owned();
         error!("{}", &msg);
@@ -114,6 +334,95 @@ fn runner_config(m: &ArgMatches) -> Result<AppConfig> {
     }
 }
 
+fn check_config(m: &ArgMatches) -> Result<Mode> {
+    if let Some(value) = m.get_one::<String>("config") {
+        let path = PathBuf::from(value);
+        Ok(Mode::Check { path })
+    } else {
+        let msg = "Failed to read arguments for 'check' subcommand".to_owned();
+        error!("{}", &msg);
+        Err(anyhow!("{}", msg))
+    }
+}
+
+fn build_config(m: &ArgMatches) -> Result<Mode> {
+    if let (Some(config), Some(out)) = (m.get_one::<String>("config"), m.get_one::<String>("out")) {
+        let app = check::load(config)?;
+        let out = PathBuf::from(out);
+        Ok(Mode::Build { app, out })
+    } else {
+        let msg = "Failed to read arguments for 'build' subcommand".to_owned();
+        error!("{}", &msg);
+        Err(anyhow!("{}", msg))
+    }
+}
+
+fn topic_command(m: &ArgMatches) -> Result<Mode> {
+    let (action, sub_m) = match m.subcommand() {
+        Some(("add", sub_m)) => (TopicAction::Add, sub_m),
+        Some(("remove", sub_m)) => (
+            TopicAction::Remove {
+                purge: sub_m.get_flag("purge"),
+            },
+            sub_m,
+        ),
+        _ => return Err(anyhow!("Failed to read arguments for 'topic' subcommand")),
+    };
+
+    if let (Some(path), Some(name)) = (
+        sub_m.get_one::<String>("config"),
+        sub_m.get_one::<String>("name"),
+    ) {
+        Ok(Mode::Topic(TopicCommand {
+            path: PathBuf::from(path),
+            name: name.to_owned(),
+            action,
+            dry_run: sub_m.get_flag("dry-run"),
+        }))
+    } else {
+        let msg = "Failed to read arguments for 'topic' subcommand".to_owned();
+        error!("{}", &msg);
+        Err(anyhow!("{}", msg))
+    }
+}
+
+fn new_seed(m: &ArgMatches) -> Result<Seed> {
+    let mut seed = match m.get_one::<String>("from") {
+        Some(path) => Seed::from_path(path)?,
+        None => Seed::default(),
+    };
+
+    let flag = |id: &str| m.get_one::<String>(id).cloned();
+    seed.name = flag("name").or(seed.name);
+    seed.author = flag("author").or(seed.author);
+    seed.url = flag("url").or(seed.url);
+    seed.topics = flag("topics").map(|t| csv_to_vec(&t)).or(seed.topics);
+    seed.bind = flag("bind").or(seed.bind);
+    seed.port = m.get_one::<u16>("port").copied().or(seed.port);
+    seed.template = flag("template").or(seed.template);
+    seed.dir = m.get_one::<PathBuf>("dir").cloned().or(seed.dir);
+
+    trace!("Seed: {:?}", seed);
+    Ok(seed)
+}
+
+/// Returns `value`, or prompts for it when a `reader` is available.
+fn value_or_input<R: BufRead>(
+    value: Option<String>,
+    flag: &str,
+    prompt: &str,
+    reader: &mut Option<&mut R>,
+) -> Result<String> {
+    match (value, reader) {
+        (Some(value), _) => Ok(value),
+        (None, Some(reader)) => get_input(prompt, *reader),
+        (None, None) => Err(anyhow!(
+            "no value given for '{}', and stdin is not a terminal to prompt for one",
+            flag
+        )),
+    }
+}
+
 fn get_input<R: BufRead>(prompt: &str, reader: &mut R) -> Result<String> {
     let mut buf = String::new();
     println!("{}", prompt);
@@ -136,11 +445,68 @@ fn csv_to_vec(csv: &str) -> Vec<String> {
                 .trim_end_matches(char::is_whitespace)
                 .to_string()
         })
+        .filter(|s| !s.is_empty())
         .collect();
 
     val_vec
 }
 
+/// Values for a new site, given by `arse new` flags or a `--from` seed TOML file.
+///
+/// Every field is optional. Missing `name`, `author`, `url`, and `topics` are prompted for
+/// when possible, and the rest fall back to defaults.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Seed {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub url: Option<String>,
+    pub topics: Option<Vec<String>>,
+    pub bind: Option<String>,
+    pub port: Option<u16>,
+    pub template: Option<String>,
+    pub dir: Option<PathBuf>,
+}
+
+impl Seed {
+    /// Loads a [`Seed`] from a TOML file.
+    pub(crate) fn from_path<P: AsRef<Path>>(seed: P) -> Result<Seed> {
+        debug!("Loading site seed from {}", &seed.as_ref().display());
+        let seed_string = std::fs::read_to_string(&seed)
+            .with_context(|| format!("failed reading '{}' to string", &seed.as_ref().display()))?;
+        toml::from_str(&seed_string).context("failed to parse seed TOML")
+    }
+
+    /// Generates a new site from this [`Seed`], prompting on stdin for missing values only if it is a terminal.
+    pub(crate) fn generate(self) -> Result<AppConfig> {
+        let current_path =
+            std::env::current_dir().context("failed to get current working directory")?;
+        let dir = match &self.dir {
+            Some(dir) => current_path.join(dir),
+            None => current_path,
+        };
+        create_dir_all(&dir).with_context(|| format!("failed to create '{}'", dir.display()))?;
+
+        let stdin = std::io::stdin();
+        if stdin.is_terminal() {
+            AppConfig::generate_from(self, dir, Some(&mut stdin.lock()))
+        } else {
+            AppConfig::generate_from(self, dir, None::<&mut StdinLock>)
+        }
+    }
+}
+
+/// Strategies for ordering a topic's posts, newest first.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PostOrder {
+    /// By publish date from post metadata, a `YYYY-MM-DD` filename prefix, or file modification time.
+    #[default]
+    Date,
+    /// By filename, in reverse-lexical order.
+    Filename,
+}
+
 /// Contains the site's name, author, rendering template, and topics.
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub(crate) struct Site {
@@ -149,43 +515,413 @@ pub(crate) struct Site {
     pub url: String,
     pub template: String,
     pub topics: Vec<String>,
+    /// Per-topic [`PostOrder`], keyed by topic name or `main`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ordering: HashMap<String, PostOrder>,
+    /// Posts per topic page. All posts are rendered on one page when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posts_per_page: Option<usize>,
+    /// Whether the default template lists post excerpts rather than full posts on topic pages.
+    #[serde(default)]
+    pub excerpts: bool,
+    /// Top-level paragraphs included in a post's excerpt when it has no `<!-- more -->` marker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt_paragraphs: Option<usize>,
 }
 
 impl Site {
-    /// Creates a new [`Site`] from user input.
-    pub(crate) fn new_from_input<R: BufRead>(reader: &mut R) -> Result<Site> {
-        let name = get_input("Please enter a name for the site: ", reader)?;
-        let author = get_input("Please enter the site author's name: ", reader)?;
-        let url = get_input("Please enter the base URL for your site: ", reader)?;
-        let topics = get_input("Please enter comma-separated site topics: ", reader)?;
-        let topics = csv_to_vec(&topics);
-        let template = "default.tmpl".to_owned();
+    /// Creates a new [`Site`] from a [`Seed`], prompting for missing values when given a `reader`.
+    pub(crate) fn from_seed<R: BufRead>(seed: &Seed, mut reader: Option<&mut R>) -> Result<Site> {
+        let name = value_or_input(
+            seed.name.clone(),
+            "name",
+            "Please enter a name for the site: ",
+            &mut reader,
+        )?;
+        let author = value_or_input(
+            seed.author.clone(),
+            "author",
+            "Please enter the site author's name: ",
+            &mut reader,
+        )?;
+        let url = value_or_input(
+            seed.url.clone(),
+            "url",
+            "Please enter the base URL for your site: ",
+            &mut reader,
+        )?;
+        let topics = match &seed.topics {
+            Some(topics) => topics.clone(),
+            None => csv_to_vec(&value_or_input(
+                None,
+                "topics",
+                "Please enter comma-separated site topics: ",
+                &mut reader,
+            )?),
+        };
+        let template = seed
+            .template
+            .clone()
+            .unwrap_or_else(|| "default.tmpl".to_owned());
         let site = Site {
             name,
             author,
             url,
             template,
             topics,
+            ordering: HashMap::new(),
+            posts_per_page: None,
+            excerpts: false,
+            excerpt_paragraphs: None,
         };
 
         trace!("Site: {:?}", site);
         Ok(site)
     }
+
+    /// Returns the configured `excerpt_paragraphs`, or `1` if unset.
+    pub(crate) fn excerpt_paragraphs(&self) -> usize {
+        self.excerpt_paragraphs.unwrap_or(1)
+    }
+
+    /// Returns the [`PostOrder`] configured for a topic slug, or the default if none is set.
+    ///
+    /// An entry keyed by the slug itself, such as `main`, is preferred over one keyed by the name
+    /// of the topic with that slug.
+    pub(crate) fn order_for(&self, topic_slug: &str) -> PostOrder {
+        self.ordering
+            .get(topic_slug)
+            .or_else(|| {
+                self.topics
+                    .iter()
+                    .find(|topic| common::slugify(topic) == topic_slug)
+                    .and_then(|topic| self.ordering.get(topic))
+            })
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
-/// Contains server configuration parameters: bind address, and port.
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+/// Contains server configuration parameters: bind address, port, additional listeners, and optional TLS.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub(crate) struct Server {
     pub bind: String,
     pub port: u16,
+    /// Addresses to listen on in place of `bind` and `port`, when not empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listen: Vec<Listen>,
+    /// Permissions applied to Unix domain sockets created for `listen`, such as `0o660`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket_mode: Option<u32>,
+    /// Serves HTTPS on every TCP listener when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
 }
 
 impl Server {
-    /// Creates a new [`Server`] instance with defaults: `0.0.0.0:9090`.
+    /// Creates a new [`Server`] instance with defaults: `0.0.0.0:9090`, without TLS.
     pub(crate) fn new() -> Server {
         Server {
             bind: "0.0.0.0".to_owned(),
             port: 9090,
+            listen: Vec::new(),
+            socket_mode: None,
+            tls: None,
+        }
+    }
+}
+
+/// An address from `server.listen`: either a TCP socket address, or `unix:` followed by a socket path.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) enum Listen {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl TryFrom<String> for Listen {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Listen, String> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(format!("'{}' is missing a socket path", value));
+            }
+            return Ok(Listen::Unix(PathBuf::from(path)));
+        }
+        value.parse().map(Listen::Tcp).map_err(|_| {
+            format!(
+                "'{}' is neither 'unix:/path/to.sock' nor an address such as '0.0.0.0:9090'",
+                value
+            )
+        })
+    }
+}
+
+impl From<Listen> for String {
+    fn from(listen: Listen) -> String {
+        listen.to_string()
+    }
+}
+
+impl fmt::Display for Listen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listen::Tcp(addr) => write!(f, "{}", addr),
+            Listen::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Contains HTTPS settings from `[server.tls]`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct Tls {
+    /// Path to the PEM encoded certificate chain, leaf certificate first.
+    pub cert: String,
+    /// Path to the PEM encoded private key.
+    pub key: String,
+    /// Port of a plain HTTP listener on `server.bind` that redirects to HTTPS. Disabled when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_port: Option<u16>,
+}
+
+/// Contains syndication feed settings shared by the RSS, Atom, and JSON feeds.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct Feed {
+    /// Maximum number of posts in each feed, newest first. Unlimited when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    /// Whether feed items carry only each post's excerpt rather than its full HTML.
+    pub excerpt_only: bool,
+}
+
+/// Contains the rules written to `/robots.txt` for all crawlers.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct Robots {
+    /// Crawler the rules apply to. Default: `*`
+    pub user_agent: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disallow: Vec<String>,
+    /// Whether to reference `/sitemap.xml` under `site.url`. Default: `true`
+    pub sitemap: bool,
+}
+
+impl Default for Robots {
+    fn default() -> Robots {
+        Robots {
+            user_agent: "*".to_owned(),
+            allow: Vec::new(),
+            disallow: Vec::new(),
+            sitemap: true,
+        }
+    }
+}
+
+/// Contains the settings for negotiated gzip and brotli compression of responses.
+///
+/// Changes take effect after a restart.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct Compression {
+    /// Whether responses are compressed for clients that accept it. Default: `true`
+    pub enabled: bool,
+    /// Smallest response, in bytes, that is compressed, up to `65535`. Default: `1024`
+    pub min_size: usize,
+    /// MIME types that are compressed, where `type/*` matches any subtype.
+    pub mime_types: Vec<String>,
+}
+
+impl Default for Compression {
+    fn default() -> Compression {
+        let mime_types = [
+            "text/*",
+            "application/javascript",
+            "application/json",
+            "application/feed+json",
+            "application/rss+xml",
+            "application/atom+xml",
+            "application/xml",
+            "image/svg+xml",
+        ];
+        Compression {
+            enabled: true,
+            min_size: 1024,
+            mime_types: mime_types.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+}
+
+impl Compression {
+    /// Returns `min_size`, limited to the largest threshold the compression layer supports.
+    pub(crate) fn size_threshold(&self) -> u16 {
+        u16::try_from(self.min_size).unwrap_or_else(|_| {
+            warn!(
+                "compression.min_size {} is above the maximum of {}, using the maximum",
+                self.min_size,
+                u16::MAX
+            );
+            u16::MAX
+        })
+    }
+
+    /// Whether a response with `content_type` may be compressed.
+    pub(crate) fn allows(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        self.mime_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(kind) => essence
+                    .split_once('/')
+                    .is_some_and(|(essence_kind, _)| essence_kind == kind),
+                None => essence == allowed.as_str(),
+            })
+    }
+}
+
+/// A `Cache-Control` policy, sent only when at least one directive is set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct CachePolicy {
+    /// Seconds a response may be reused without revalidating it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+    /// Whether a response never changes while fresh, so clients skip revalidating it on reload.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub immutable: bool,
+    /// Whether a response must be revalidated before each reuse.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub no_cache: bool,
+}
+
+impl CachePolicy {
+    /// Whether the policy sets no directive, and so sends no header.
+    fn is_empty(&self) -> bool {
+        *self == CachePolicy::default()
+    }
+
+    /// Returns the `Cache-Control` header value for the policy, if it sets any directive.
+    pub(crate) fn header_value(&self) -> Option<String> {
+        let mut directives = Vec::new();
+        if self.no_cache {
+            directives.push("no-cache".to_owned());
+        }
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age));
+        }
+        if self.immutable {
+            directives.push("immutable".to_owned());
+        }
+        (!directives.is_empty()).then(|| directives.join(", "))
+    }
+}
+
+/// A [`CachePolicy`] for request paths matching the glob `path`, such as `/static/fonts/*`.
+///
+/// The glob is compiled as the configuration is loaded, so an invalid one fails to load.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "CacheOverrideToml", into = "CacheOverrideToml")]
+pub(crate) struct CacheOverride {
+    pub path: Pattern,
+    pub policy: CachePolicy,
+}
+
+/// A [`CacheOverride`] as written in the configuration TOML.
+#[derive(Deserialize, Serialize)]
+struct CacheOverrideToml {
+    path: String,
+    #[serde(flatten)]
+    policy: CachePolicy,
+}
+
+impl TryFrom<CacheOverrideToml> for CacheOverride {
+    type Error = String;
+
+    fn try_from(value: CacheOverrideToml) -> std::result::Result<CacheOverride, String> {
+        match Pattern::new(&value.path) {
+            Ok(path) => Ok(CacheOverride {
+                path,
+                policy: value.policy,
+            }),
+            Err(err) => Err(format!(
+                "cache override path '{}' is not a valid glob: {}",
+                value.path, err
+            )),
+        }
+    }
+}
+
+impl From<CacheOverride> for CacheOverrideToml {
+    fn from(value: CacheOverride) -> CacheOverrideToml {
+        CacheOverrideToml {
+            path: value.path.as_str().to_owned(),
+            policy: value.policy,
+        }
+    }
+}
+
+/// Classes of routes given separate policies by [`CacheControl`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RouteClass {
+    /// Rendered HTML pages, posts, and search results.
+    Page,
+    /// Feeds, sitemaps, and `robots.txt`.
+    Feed,
+    /// Files under `static/` and `.well-known/`, and `favicon.ico`.
+    Static,
+    /// Files under each topic's `ext/`.
+    Ext,
+}
+
+/// Contains the `Cache-Control` policies for each [`RouteClass`], and overrides for matching paths.
+///
+/// Every policy is empty by default, so no `Cache-Control` header is sent until one is configured.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct CacheControl {
+    #[serde(skip_serializing_if = "CachePolicy::is_empty")]
+    pub pages: CachePolicy,
+    #[serde(skip_serializing_if = "CachePolicy::is_empty")]
+    pub feeds: CachePolicy,
+    #[serde(rename = "static", skip_serializing_if = "CachePolicy::is_empty")]
+    pub static_files: CachePolicy,
+    #[serde(skip_serializing_if = "CachePolicy::is_empty")]
+    pub ext: CachePolicy,
+    /// Checked in order before the class policies, with the first matching path used.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<CacheOverride>,
+}
+
+impl CacheControl {
+    /// Returns the policy for a request to `path` served by a route of `class`.
+    ///
+    /// Override globs match whole path segments, so `*` stays within one segment and `**` spans any.
+    pub(crate) fn policy(&self, class: RouteClass, path: &str) -> &CachePolicy {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::default()
+        };
+        let matched = self
+            .overrides
+            .iter()
+            .find(|item| item.path.matches_with(path, options));
+        if let Some(item) = matched {
+            return &item.policy;
+        }
+
+        match class {
+            RouteClass::Page => &self.pages,
+            RouteClass::Feed => &self.feeds,
+            RouteClass::Static => &self.static_files,
+            RouteClass::Ext => &self.ext,
         }
     }
 }
@@ -219,6 +955,14 @@ pub(crate) struct AppConfig {
     pub site: Site,
     pub server: Server,
     pub docpaths: DocPaths,
+    #[serde(default)]
+    pub feed: Feed,
+    #[serde(default)]
+    pub robots: Robots,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub cache: CacheControl,
     pub mime_types: HashMap<String, String>,
 }
 
@@ -240,15 +984,24 @@ impl AppConfig {
         Ok(app_config)
     }
 
-    /// Generates a new [`AppConfig`] from user input, and creates necessary [`DocPaths`] paths on disk.
-    pub(crate) fn generate<P: AsRef<Path>, R: BufRead>(
+    /// Generates a new [`AppConfig`] from a [`Seed`], and creates necessary [`DocPaths`] paths on disk.
+    ///
+    /// Values missing from the [`Seed`] are read from `reader`, or are an error without one.
+    pub(crate) fn generate_from<P: AsRef<Path>, R: BufRead>(
+        seed: Seed,
         dir: P,
-        reader: &mut R,
+        reader: Option<&mut R>,
     ) -> Result<AppConfig> {
         info!("Generating new site configuration");
         let docpaths = DocPaths::new(&dir);
-        let site = Site::new_from_input(reader)?;
-        let server = Server::new();
+        let site = Site::from_seed(&seed, reader)?;
+        let mut server = Server::new();
+        if let Some(bind) = seed.bind {
+            server.bind = bind;
+        }
+        if let Some(port) = seed.port {
+            server.port = port;
+        }
         let mime_types: HashMap<String, String> = HashMap::from([
             ("css".into(), "text/css".into()),
             ("gif".into(), "image/gif".into()),
@@ -259,6 +1012,10 @@ impl AppConfig {
             site,
             server,
             docpaths,
+            feed: Feed::default(),
+            robots: Robots::default(),
+            compression: Compression::default(),
+            cache: CacheControl::default(),
             mime_types,
         };
 
@@ -276,24 +1033,72 @@ impl AppConfig {
         info!("Creating site filesystem tree");
         create_dir_all(&self.docpaths.templates)?;
         create_dir_all(format!("{}/static", &self.docpaths.webroot))?;
-        create_dir_all(format!("{}/main/ext", &self.docpaths.webroot))?;
-        create_dir_all(format!("{}/main/posts", &self.docpaths.webroot))?;
+        self.create_topic_paths("main")?;
 
         for topic in &self.site.topics {
-            let topic = common::slugify(topic);
+            self.create_topic_paths(&common::slugify(topic))?;
+        }
+        Ok(())
+    }
+
+    /// Creates the `ext` and `posts` directories for a topic slug.
+    fn create_topic_paths(&self, topic_slug: &str) -> Result<()> {
+        create_dir_all(format!("{}/{}/ext", &self.docpaths.webroot, topic_slug))?;
+        create_dir_all(format!("{}/{}/posts", &self.docpaths.webroot, topic_slug))?;
+        Ok(())
+    }
 
-            create_dir_all(format!("{}/{}/ext", &self.docpaths.webroot, &topic))?;
-            create_dir_all(format!("{}/{}/posts", &self.docpaths.webroot, &topic))?;
+    /// Adds a topic to `site.topics`, unless its slug is already in use.
+    fn add_topic(&mut self, name: &str) -> Result<()> {
+        let slug = common::slugify(name);
+        if slug.is_empty() {
+            return Err(anyhow!("'{}' is not a valid topic name", name));
+        }
+        if common::is_reserved_slug(&slug) {
+            return Err(anyhow!(
+                "topic '{}' would be shadowed by the built-in '/{}' route",
+                name,
+                slug
+            ));
+        }
+        if let Some(existing) = self.site.topics.iter().find(|t| common::slugify(t) == slug) {
+            return Err(anyhow!(
+                "topic '{}' conflicts with existing topic '{}'",
+                name,
+                existing
+            ));
         }
+
+        self.site.topics.push(name.to_owned());
         Ok(())
     }
 
+    /// Removes the topic matching `name` by slug from `site.topics`, returning its configured name.
+    fn remove_topic(&mut self, name: &str) -> Result<String> {
+        let slug = common::slugify(name);
+        let idx = self
+            .site
+            .topics
+            .iter()
+            .position(|t| common::slugify(t) == slug)
+            .ok_or_else(|| anyhow!("topic '{}' was not found", name))?;
+        Ok(self.site.topics.remove(idx))
+    }
+
     /// Writes an [`AppConfig`] to disk in the current working directory as `config.toml`.
     fn write<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        self.write_to(dir.as_ref().join("config.toml"))
+    }
+
+    /// Writes an [`AppConfig`] to disk at `path`, replacing any existing file.
+    ///
+    /// The file is replaced atomically, so a watcher never sees a half-written config. It is
+    /// regenerated from the parsed values, so any comments or custom key order in the original
+    /// are not kept.
+    fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         info!("Writing site configuration to disk");
         let config = toml::to_string_pretty(&self).context("failure creating TOML")?;
-        let conf_path = &dir.as_ref().join("config.toml");
-        common::str_to_ro_file(&config, conf_path)?;
+        common::replace_ro_file(&config, path)?;
         Ok(())
     }
 }
@@ -301,6 +1106,7 @@ impl AppConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing;
 
     #[test]
     fn build_run_config() {
@@ -310,6 +1116,31 @@ mod tests {
             let config = runner_config(run_m);
             assert!(config.is_ok());
         }
+
+        let arg_vec = vec!["arse", "run", "./test_files/test-config.toml", "--drafts"];
+        let matches = args().get_matches_from(arg_vec);
+        let run_m = matches.subcommand_matches("run").unwrap();
+        assert!(matches!(
+            runner_config(run_m),
+            Ok(Mode::Run { drafts: true, .. })
+        ));
+    }
+
+    #[test]
+    fn build_export_config() {
+        let arg_vec = vec![
+            "arse",
+            "build",
+            "./test_files/test-config.toml",
+            "--out",
+            "public",
+        ];
+        let matches = args().get_matches_from(arg_vec);
+        let build_m = matches.subcommand_matches("build").unwrap();
+        match build_config(build_m).unwrap() {
+            Mode::Build { out, .. } => assert_eq!(out, PathBuf::from("public")),
+            mode => panic!("unexpected mode: {:?}", mode),
+        }
     }
 
     #[test]
@@ -318,7 +1149,7 @@ mod tests {
         // Setup all target fields
         let mut src: &[u8] =
             b"Site Name\nAuthor Name\nhttps://my.example.site\nOne, Two, Three, And More\n";
-        let config = AppConfig::generate(&dir, &mut src);
+        let config = AppConfig::generate_from(Seed::default(), &dir, Some(&mut src));
         assert!(config.is_ok());
 
         let tmp_dir = &dir.path();
@@ -359,6 +1190,305 @@ mod tests {
         }
     }
 
+    #[test]
+    fn topic_ordering() {
+        let config = r#"
+[site]
+name = "Site"
+author = "Author"
+url = "https://example.com"
+template = "default.tmpl"
+topics = ["One", "And More"]
+
+[site.ordering]
+main = "filename"
+"And More" = "date"
+"and-more" = "filename"
+"ONE" = "filename"
+
+[server]
+bind = "0.0.0.0"
+port = 9090
+
+[docpaths]
+templates = "site/templates"
+webroot = "site/webroot"
+
+[mime_types]
+"#;
+        let app: AppConfig = toml::from_str(config).unwrap();
+        assert_eq!(app.site.order_for("main"), PostOrder::Filename);
+        assert_eq!(app.site.order_for("and-more"), PostOrder::Filename);
+        assert_eq!(app.site.order_for("one"), PostOrder::Date);
+        assert_eq!(app.site.order_for("two"), PostOrder::Date);
+    }
+
+    #[test]
+    fn cache_control_policies() {
+        let cache = CacheControl::default();
+        let policy = |class, path| cache.policy(class, path).header_value();
+        assert_eq!(policy(RouteClass::Page, "/"), None);
+        assert_eq!(policy(RouteClass::Static, "/static/a.css"), None);
+
+        let cache: CacheControl = toml::from_str(
+            r#"
+pages = { max_age = 60 }
+feeds = { max_age = 900 }
+static = { max_age = 3600 }
+
+[[overrides]]
+path = "/static/fonts/*"
+max_age = 31536000
+immutable = true
+
+[[overrides]]
+path = "/one/ext/**/*.mp4"
+no_cache = true
+"#,
+        )
+        .unwrap();
+        let policy = |class, path| cache.policy(class, path).header_value();
+        assert_eq!(policy(RouteClass::Page, "/").as_deref(), Some("max-age=60"));
+        assert_eq!(
+            policy(RouteClass::Feed, "/rss.xml").as_deref(),
+            Some("max-age=900")
+        );
+        assert_eq!(
+            policy(RouteClass::Static, "/static/fonts/a.woff2").as_deref(),
+            Some("max-age=31536000, immutable")
+        );
+        assert_eq!(
+            policy(RouteClass::Static, "/static/fonts/old/a.woff2").as_deref(),
+            Some("max-age=3600")
+        );
+        assert_eq!(policy(RouteClass::Ext, "/one/ext/a.png"), None);
+        assert_eq!(
+            policy(RouteClass::Ext, "/one/ext/videos/a.mp4").as_deref(),
+            Some("no-cache")
+        );
+        assert_eq!(
+            toml::from_str::<CacheControl>(&toml::to_string(&cache).unwrap()).unwrap(),
+            cache
+        );
+
+        let err =
+            toml::from_str::<CacheControl>("[[overrides]]\npath = \"/static/[a\"\n").unwrap_err();
+        assert!(err
+            .message()
+            .starts_with("cache override path '/static/[a' is not a valid glob"));
+    }
+
+    #[test]
+    fn compression_mime_types() {
+        let compression = Compression::default();
+        assert!(compression.allows("text/html"));
+        assert!(compression.allows("text/css; charset=utf-8"));
+        assert!(compression.allows("application/rss+xml"));
+        assert!(!compression.allows("image/png"));
+        assert!(!compression.allows("textual/html"));
+
+        let compression: Compression = toml::from_str("mime_types = [\"text/html\"]").unwrap();
+        assert!(compression.enabled);
+        assert_eq!(compression.min_size, 1024);
+        assert_eq!(compression.size_threshold(), 1024);
+        let large: Compression = toml::from_str("min_size = 1048576").unwrap();
+        assert_eq!(large.size_threshold(), u16::MAX);
+        assert!(compression.allows("TEXT/HTML"));
+        assert!(!compression.allows("text/css"));
+    }
+
+    #[test]
+    fn add_and_remove_topics() {
+        let dir = tempfile::tempdir().unwrap();
+        testing::site_at(&dir, "https://my.example.site", &["One"]);
+        let path = dir.path().join("config.toml");
+        let webroot = dir.path().join("site/webroot");
+        let command = |name: &str, action: TopicAction, dry_run: bool| TopicCommand {
+            path: path.clone(),
+            name: name.to_owned(),
+            action,
+            dry_run,
+        };
+
+        command("And More", TopicAction::Add, true).apply().unwrap();
+        assert_eq!(
+            AppConfig::from_path(&path).unwrap().site.topics,
+            vec!["One"]
+        );
+        assert!(!webroot.join("and-more").exists());
+
+        command("And More", TopicAction::Add, false)
+            .apply()
+            .unwrap();
+        assert_eq!(
+            AppConfig::from_path(&path).unwrap().site.topics,
+            vec!["One", "And More"]
+        );
+        assert!(webroot.join("and-more/ext").exists());
+        assert!(webroot.join("and-more/posts").exists());
+        assert!(command("and-more", TopicAction::Add, false)
+            .apply()
+            .is_err());
+        for reserved in ["Main", "Static", "tags", "Archive", "search", "page"] {
+            assert!(command(reserved, TopicAction::Add, false).apply().is_err());
+        }
+
+        let purge = TopicAction::Remove { purge: true };
+        command("and-more", purge, true).apply().unwrap();
+        assert!(webroot.join("and-more").exists());
+
+        command("one", TopicAction::Remove { purge: false }, false)
+            .apply()
+            .unwrap();
+        assert!(webroot.join("one/posts").exists());
+
+        let purge = TopicAction::Remove { purge: true };
+        command("and-more", purge, false).apply().unwrap();
+        assert!(AppConfig::from_path(&path).unwrap().site.topics.is_empty());
+        assert!(!webroot.join("and-more").exists());
+        assert!(!dir.path().join(".config.toml.tmp").exists());
+        assert!(command("nope", TopicAction::Remove { purge: false }, false)
+            .apply()
+            .is_err());
+
+        let mut app = AppConfig::from_path(&path).unwrap();
+        app.site.topics.push("Static".to_owned());
+        app.write_to(&path).unwrap();
+        let purge = TopicAction::Remove { purge: true };
+        assert!(command("static", purge, false).apply().is_err());
+        assert!(webroot.join("static").exists());
+        assert_eq!(
+            AppConfig::from_path(&path).unwrap().site.topics,
+            vec!["Static"]
+        );
+    }
+
+    #[test]
+    fn build_config_from_seed() {
+        let dir = tempfile::tempdir().unwrap();
+        let seed_path = dir.path().join("seed.toml");
+        let seed = r#"
+name = "Seeded Site"
+author = "Seed Author"
+url = "https://seed.example.site"
+topics = ["One", "And More"]
+port = 8080
+template = "custom.tmpl"
+"#;
+        std::fs::write(&seed_path, seed).unwrap();
+
+        let site_dir = dir.path().join("site-dir");
+        let arg_vec = vec![
+            "arse".to_owned(),
+            "new".to_owned(),
+            "--from".to_owned(),
+            seed_path.display().to_string(),
+            "--name".to_owned(),
+            "Flag Site".to_owned(),
+            "--bind".to_owned(),
+            "127.0.0.1".to_owned(),
+            "--dir".to_owned(),
+            site_dir.display().to_string(),
+        ];
+        let matches = args().get_matches_from(arg_vec);
+        let seed = new_seed(matches.subcommand_matches("new").unwrap()).unwrap();
+        assert_eq!(seed.name.as_deref(), Some("Flag Site"));
+        assert_eq!(seed.author.as_deref(), Some("Seed Author"));
+
+        let config = AppConfig::generate_from(seed, &site_dir, None::<&mut &[u8]>).unwrap();
+        assert_eq!(config.site.name, "Flag Site");
+        assert_eq!(config.site.template, "custom.tmpl");
+        assert_eq!(config.site.topics, vec!["One", "And More"]);
+        assert_eq!(config.server.bind, "127.0.0.1");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(
+            AppConfig::from_path(site_dir.join("config.toml")).unwrap(),
+            config
+        );
+        assert!(site_dir.join("site/webroot/and-more/posts").exists());
+    }
+
+    #[test]
+    fn build_config_from_partial_seed() {
+        let dir = tempfile::tempdir().unwrap();
+        let seed = Seed {
+            name: Some("Site Name".to_owned()),
+            topics: Some(vec!["One".to_owned()]),
+            ..Default::default()
+        };
+        assert!(AppConfig::generate_from(seed, &dir, None::<&mut &[u8]>).is_err());
+        assert!(!dir.path().join("config.toml").exists());
+
+        let seed = Seed {
+            name: Some("Site Name".to_owned()),
+            topics: Some(vec!["One".to_owned()]),
+            ..Default::default()
+        };
+        let mut src: &[u8] = b"Author Name\nhttps://my.example.site\n";
+        let config = AppConfig::generate_from(seed, &dir, Some(&mut src)).unwrap();
+        assert_eq!(config.site.name, "Site Name");
+        assert_eq!(config.site.author, "Author Name");
+        assert_eq!(config.site.url, "https://my.example.site");
+        assert_eq!(config.server, Server::new());
+    }
+
+    #[test]
+    fn server_listen_addresses() {
+        let server: Server = toml::from_str(
+            r#"
+bind = '0.0.0.0'
+port = 9090
+listen = ['0.0.0.0:8080', '[::]:8080', 'unix:/run/arse.sock']
+socket_mode = 0o660
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            server.listen,
+            [
+                Listen::Tcp("0.0.0.0:8080".parse().unwrap()),
+                Listen::Tcp("[::]:8080".parse().unwrap()),
+                Listen::Unix(PathBuf::from("/run/arse.sock")),
+            ]
+        );
+        assert_eq!(server.socket_mode, Some(0o660));
+        assert_eq!(server.listen[2].to_string(), "unix:/run/arse.sock");
+
+        let round_trip: Server = toml::from_str(&toml::to_string(&server).unwrap()).unwrap();
+        assert_eq!(round_trip, server);
+
+        for bad in ["unix:", "localhost", "0.0.0.0"] {
+            let src = format!("bind = '::'\nport = 80\nlisten = ['{}']\n", bad);
+            assert!(toml::from_str::<Server>(&src).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn server_tls_section() {
+        let server: Server = toml::from_str("bind = '::'\nport = 443\n").unwrap();
+        assert_eq!(server.tls, None);
+
+        let server: Server = toml::from_str(
+            r#"
+bind = '::'
+port = 443
+
+[tls]
+cert = '/etc/arse/fullchain.pem'
+key = '/etc/arse/privkey.pem'
+redirect_port = 80
+"#,
+        )
+        .unwrap();
+        let tls = server.tls.as_ref().unwrap();
+        assert_eq!(tls.cert, "/etc/arse/fullchain.pem");
+        assert_eq!(tls.key, "/etc/arse/privkey.pem");
+        assert_eq!(tls.redirect_port, Some(80));
+
+        let round_trip: Server = toml::from_str(&toml::to_string(&server).unwrap()).unwrap();
+        assert_eq!(round_trip, server);
+    }
+
     #[test]
     fn handle_csv_topics() {
         let reference_topics: Vec<String> = vec![
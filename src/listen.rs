@@ -0,0 +1,279 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides the sockets a running site accepts connections on.
+//!
+//! - Stream sockets inherited through systemd socket activation (`LISTEN_FDS`) are used when present.
+//! - Otherwise every address from `server.listen`, or `server.bind` and `server.port`, is bound.
+//!   Unix domain sockets replace a stale socket file left at their path, and take the
+//!   `server.socket_mode` permissions.
+
+use std::fmt;
+use std::io;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+#[cfg(target_family = "unix")]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(target_family = "unix")]
+use std::path::Path;
+
+use axum::Router;
+use listenfd::ListenFd;
+use log::{debug, error, info, warn};
+use tokio::task::JoinSet;
+
+use super::config::{Listen, Server};
+use super::{anyhow, Context, Result};
+
+/// A bound socket, ready to accept connections.
+#[derive(Debug)]
+pub(crate) enum Listener {
+    Tcp(std::net::TcpListener),
+    #[cfg(target_family = "unix")]
+    Unix(UnixListener),
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listener::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "{}", addr),
+                Err(_) => write!(f, "TCP socket"),
+            },
+            #[cfg(target_family = "unix")]
+            Listener::Unix(listener) => {
+                let addr = listener.local_addr();
+                match addr.as_ref().ok().and_then(|addr| addr.as_pathname()) {
+                    Some(path) => write!(f, "unix:{}", path.display()),
+                    None => write!(f, "Unix domain socket"),
+                }
+            }
+        }
+    }
+}
+
+impl Listener {
+    /// Serves `router` over plain HTTP until an unrecoverable error occurs.
+    pub(crate) async fn serve(self, router: Router) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                axum::serve(listener, router).await
+            }
+            #[cfg(target_family = "unix")]
+            Listener::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                let listener = tokio::net::UnixListener::from_std(listener)?;
+                axum::serve(listener, router).await
+            }
+        }
+    }
+}
+
+/// Binds the sockets for `server`, preferring any inherited through systemd socket activation.
+pub(crate) fn bind(server: &Server) -> Result<Vec<Listener>> {
+    let inherited = inherited(ListenFd::from_env())?;
+    if !inherited.is_empty() {
+        info!("Using {} socket(s) from systemd", inherited.len());
+        if !server.listen.is_empty() {
+            warn!("Ignoring server.listen in favor of the sockets from systemd");
+        }
+        return Ok(inherited);
+    }
+
+    if server.listen.is_empty() {
+        return bind_default(server).map(|listener| vec![listener]);
+    }
+    server
+        .listen
+        .iter()
+        .map(|addr| bind_addr(addr, server.socket_mode))
+        .collect()
+}
+
+/// Binds `server.bind` and `server.port`, trying each address a host name resolves to until one binds.
+fn bind_default(server: &Server) -> Result<Listener> {
+    let addr = format!("{}:{}", server.bind, server.port);
+    debug!("Binding '{}'", addr);
+    std::net::TcpListener::bind((server.bind.as_str(), server.port))
+        .map(Listener::Tcp)
+        .with_context(|| format!("failed to bind '{}'", addr))
+}
+
+/// Takes every stream socket passed through `LISTEN_FDS`, in order.
+fn inherited(mut fds: ListenFd) -> Result<Vec<Listener>> {
+    let mut listeners = Vec::new();
+    for idx in 0..fds.len() {
+        if let Ok(Some(listener)) = fds.take_tcp_listener(idx) {
+            listeners.push(Listener::Tcp(listener));
+            continue;
+        }
+        #[cfg(target_family = "unix")]
+        if let Ok(Some(listener)) = fds.take_unix_listener(idx) {
+            listeners.push(Listener::Unix(listener));
+            continue;
+        }
+        return Err(anyhow!(
+            "inherited socket {} is not a TCP or Unix stream listener",
+            idx
+        ));
+    }
+    Ok(listeners)
+}
+
+#[cfg_attr(not(target_family = "unix"), allow(unused_variables))]
+fn bind_addr(addr: &Listen, mode: Option<u32>) -> Result<Listener> {
+    debug!("Binding '{}'", addr);
+    match addr {
+        Listen::Tcp(tcp) => std::net::TcpListener::bind(tcp)
+            .map(Listener::Tcp)
+            .with_context(|| format!("failed to bind '{}'", addr)),
+        #[cfg(target_family = "unix")]
+        Listen::Unix(path) => bind_unix(path, mode)
+            .map(Listener::Unix)
+            .with_context(|| format!("failed to bind '{}'", addr)),
+        #[cfg(not(target_family = "unix"))]
+        Listen::Unix(_) => Err(anyhow!(
+            "'{}' requires Unix domain sockets, which are unsupported on this platform",
+            addr
+        )),
+    }
+}
+
+/// Binds a Unix domain socket at `path`, replacing a stale socket that nothing is listening on.
+#[cfg(target_family = "unix")]
+fn bind_unix(path: &Path, mode: Option<u32>) -> io::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "socket is in use by another process",
+                ));
+            }
+            debug!("Removing stale socket '{}'", path.display());
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "path exists and is not a socket",
+            ));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+/// Serves `router` over plain HTTP on every listener until all have stopped.
+pub(crate) async fn serve(listeners: Vec<Listener>, router: Router) {
+    let mut servers = JoinSet::new();
+    for listener in listeners {
+        info!("Running server on: {}", listener);
+        servers.spawn(listener.serve(router.clone()));
+    }
+    wait(servers).await
+}
+
+/// Waits for every server in `servers` to stop, logging any errors.
+pub(crate) async fn wait(mut servers: JoinSet<io::Result<()>>) {
+    while let Some(res) = servers.join_next().await {
+        match res {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => error!("Unhandled server error: {}", err),
+            Err(err) => error!("Server task failed: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn bind_tcp_addresses() {
+        let server = Server {
+            listen: vec![
+                Listen::Tcp("127.0.0.1:0".parse().unwrap()),
+                Listen::Tcp("127.0.0.1:0".parse().unwrap()),
+            ],
+            ..Server::new()
+        };
+        let listeners = bind(&server).unwrap();
+        assert_eq!(listeners.len(), 2);
+        assert!(listeners.iter().all(|l| matches!(l, Listener::Tcp(_))));
+        assert!(listeners[0].to_string().starts_with("127.0.0.1:"));
+        assert!(inherited(ListenFd::empty()).unwrap().is_empty());
+
+        for host in ["127.0.0.1", "localhost"] {
+            let server = Server {
+                bind: host.to_owned(),
+                port: 0,
+                ..Server::new()
+            };
+            let listeners = bind(&server).unwrap();
+            assert_eq!(listeners.len(), 1, "{}", host);
+            assert!(matches!(listeners[0], Listener::Tcp(_)));
+        }
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn serve_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("arse.sock");
+        let server = Server {
+            listen: vec![Listen::Unix(path.clone())],
+            socket_mode: Some(0o600),
+            ..Server::new()
+        };
+
+        // A socket left behind by an earlier run is replaced.
+        drop(UnixListener::bind(&path).unwrap());
+        let mut listeners = bind(&server).unwrap();
+        assert_eq!(listeners[0].to_string(), format!("unix:{}", path.display()));
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A socket still being listened on is not.
+        let err = bind(&server).unwrap_err();
+        assert!(format!("{:#}", err).contains("in use"), "{:#}", err);
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let not_socket = Server {
+            listen: vec![Listen::Unix(PathBuf::from(&file))],
+            ..Server::new()
+        };
+        assert!(bind(&not_socket).is_err());
+
+        let router = Router::new().route("/", axum::routing::get(|| async { "Unix" }));
+        let task = tokio::spawn(listeners.remove(0).serve(router));
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        task.abort();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("Unix"));
+    }
+}
@@ -11,41 +11,80 @@ copied, modified, or distributed except according to those terms.
 //! Main entry point for A Rust Site Engine.
 //!
 //! # Options
-//! - `run [config]`: Starts a server defined by the `[config]` TOML.
-//! - `new`: Creates a new `[config]` TOML from user input, and creates
-//!          the site's directory structure.
+//! - `run [config] [--drafts]`: Starts a server defined by the `[config]` TOML, optionally
+//!   showing drafts and scheduled posts.
+//! - `build [config] --out [dir]`: Exports the site defined by the `[config]` TOML
+//!   as static files in `[dir]`.
+//! - `check [config]`: Validates the `[config]` TOML and the site files it refers to.
+//! - `topic add [config] [name]`, `topic remove [config] [name]`: Adds or removes a topic
+//!   in the `[config]` TOML, along with its directories.
+//! - `new`: Creates a new `[config]` TOML from flags, a seed file, or user input,
+//!   and creates the site's directory structure.
 
-use std::sync::Arc;
+use std::path::PathBuf;
 
 use anyhow::{anyhow, Context, Error, Result};
-use log::{error, info};
+use log::{error, info, warn};
 
+mod build;
+mod check;
 mod common;
 mod config;
+mod listen;
 mod render;
 mod routes;
+#[cfg(test)]
+mod testing;
+mod tls;
+mod watch;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config = config::load()?;
-    info!("Configuration loaded");
+    match config::load()? {
+        config::Mode::Run { app, path, drafts } => {
+            info!("Configuration loaded");
+            run(app, path, drafts).await
+        }
+        config::Mode::Build { app, out } => {
+            info!("Configuration loaded");
+            let engine = render::Engine::new(app)?;
+            info!("Rendering Engine loaded");
+            build::export(&engine, &out).await
+        }
+        config::Mode::Check { path } => check::load(path).map(|_| ()),
+        config::Mode::Topic(command) => command.apply(),
+        config::Mode::New(seed) => seed.generate().map(|_| ()),
+    }
+}
 
-    let engine = Arc::new(render::Engine::new(config));
+async fn run(config: config::AppConfig, config_path: PathBuf, drafts: bool) -> Result<()> {
+    let server = config.server.clone();
+    let mut engine = render::Engine::new(config)?;
+    if drafts {
+        warn!("Serving drafts and scheduled posts");
+        engine.drafts = true;
+    }
+    let engine = render::SharedEngine::new(engine);
+    if let Err(err) = engine.load().search_index().await {
+        warn!("Failed to build search index: {:#}", err);
+    }
     info!("Rendering Engine loaded");
 
-    let router = routes::router(engine.clone());
-    info!("Route handlers loaded");
-
-    let addr = format!("{}:{}", engine.app.server.bind, engine.app.server.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let watched = engine.clone();
+    tokio::spawn(async move {
+        if let Err(err) = watch::watch(watched, config_path).await {
+            error!("Stopped watching for changes: {:#}", err)
+        }
+    });
 
-    info!("Creating server on: {}", &addr);
-    let server = axum::serve(listener, router);
+    let router = routes::router(engine);
+    info!("Route handlers loaded");
 
-    info!("Running server on: {}", &addr);
-    if let Err(err) = server.await {
-        error!("Unhandled server error: {}", err)
+    let listeners = listen::bind(&server)?;
+    if let Some(tls) = &server.tls {
+        return tls::serve(tls, listeners, router).await;
     }
+    listen::serve(listeners, router).await;
 
     Ok(())
 }
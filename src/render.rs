@@ -11,46 +11,135 @@ copied, modified, or distributed except according to those terms.
 //! Provides the rendering engine for topics and posts using [`AppConfig`], [`Tera`], and [`pulldown_cmark`].
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs::File;
+use tokio::io::AsyncReadExt;
 
 use super::common;
-use super::config::AppConfig;
-use super::{Context, Result};
+use super::config::{AppConfig, PostOrder};
+use super::{anyhow, Context, Result};
 
+use arc_swap::ArcSwap;
 use chrono::{DateTime, Utc};
-use log::{debug, trace};
-use pulldown_cmark::{html, Parser};
-use rss::{Channel, Item};
+use log::{debug, error, trace};
+use rss::{Category, Channel, Guid, Item};
+use serde::Serialize;
 use tera::{Context as TemplateContext, Tera};
 
 /// Static defaults for the rendering engine.
 mod default;
 
+/// Post metadata and front matter parsing.
+mod post;
+pub(crate) use post::Post;
+
+/// Cache of rendered output.
+mod cache;
+use cache::RenderCache;
+
+/// Tag taxonomy built from post metadata.
+mod tags;
+
+/// Chronological archive of posts.
+mod archive;
+pub(crate) use archive::Archive;
+
+/// XML sitemaps.
+mod sitemap;
+use sitemap::SitemapUrl;
+
+/// JSON Feed document types.
+mod json_feed;
+use json_feed::{Author, JsonFeed, JsonItem};
+
+/// Full-text search index.
+mod search;
+use search::SearchIndex;
+pub(crate) use search::SEARCH_INDEX_FILE;
+
+/// Position of a rendered topic page, exposed to the Tera context as `pagination`.
+#[derive(Debug, Serialize)]
+pub(crate) struct Pagination {
+    pub current: usize,
+    pub total: usize,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+}
+
+impl Pagination {
+    /// Returns the URL path for a page of a topic, where `main` is served from `/`.
+    pub(crate) fn url(topic_slug: &str, page: usize) -> String {
+        match (topic_slug, page) {
+            ("main", 1) => "/".to_owned(),
+            ("main", n) => format!("/page/{}", n),
+            (topic, 1) => format!("/{}", topic),
+            (topic, n) => format!("/{}/page/{}", topic, n),
+        }
+    }
+}
+
+/// Topic being rendered, exposed to the Tera context as `topic` on pages of topics other than `main`.
+#[derive(Debug, Serialize)]
+pub(crate) struct Topic<'a> {
+    pub name: &'a str,
+    pub slug: &'a str,
+}
+
+/// Shared handle to the current [`Engine`].
+///
+/// The [`Engine`] may be replaced at runtime, such as when the site configuration is reloaded,
+/// without interrupting requests already holding the previous one.
+#[derive(Clone, Debug)]
+pub(crate) struct SharedEngine(Arc<ArcSwap<Engine>>);
+
+impl SharedEngine {
+    /// Creates a new [`SharedEngine`] holding `engine`.
+    pub(crate) fn new(engine: Engine) -> SharedEngine {
+        SharedEngine(Arc::new(ArcSwap::from_pointee(engine)))
+    }
+
+    /// Returns the current [`Engine`].
+    pub(crate) fn load(&self) -> Arc<Engine> {
+        self.0.load_full()
+    }
+
+    /// Atomically replaces the current [`Engine`].
+    pub(crate) fn store(&self, engine: Engine) {
+        self.0.store(Arc::new(engine))
+    }
+}
+
 /// Rendering engine for topics and posts.
 ///
 /// [`Engine`] stores an [`Arc<AppConfig>`] and a [`Tera`] instance from which
-/// all rendering and serving tasks are executed.
+/// all rendering and serving tasks are executed, and a [`RenderCache`] of their output.
 #[derive(Debug)]
 pub(crate) struct Engine {
     pub app: AppConfig,
     pub instance: Tera,
     pub topic_slugs: Vec<String>,
+    pub cache: RenderCache,
+    /// Whether drafts and scheduled posts are shown, as with `arse run --drafts`.
+    pub drafts: bool,
 }
 
 impl Engine {
     /// Creates a new [`Engine`] from a given [`AppConfig`].
-    pub(crate) fn new(app: AppConfig) -> Engine {
+    pub(crate) fn new(app: AppConfig) -> Result<Engine> {
         trace!("Loading rendering engine");
-        let instance = Self::load_template(&app).unwrap();
+        let instance = Self::load_template(&app)?;
         let topic_slugs: Vec<String> = app.site.topics.iter().map(|t| common::slugify(t)).collect();
-        Engine {
+        Ok(Engine {
             app,
             instance,
             topic_slugs,
-        }
+            cache: RenderCache::default(),
+            drafts: false,
+        })
     }
 
-    fn load_template(app: &AppConfig) -> Result<Tera> {
+    /// Loads and compiles the site's [`Tera`] template.
+    pub(crate) fn load_template(app: &AppConfig) -> Result<Tera> {
         trace!("Loading Tera rendering template");
         let mut tera = Tera::default();
         let template = app.site.template.as_str();
@@ -69,22 +158,51 @@ impl Engine {
         Ok(tera)
     }
 
-    /// Renders `/:topic` content as HTML
-    pub(crate) async fn render_topic(&self, topic_slug: &str) -> Result<String> {
+    /// Renders a page of `/:topic` content as HTML
+    ///
+    /// Pages are numbered from `1`. Returns `None` when `page` is beyond the topic's last page.
+    pub(crate) async fn render_topic(
+        &self,
+        topic_slug: &str,
+        page: usize,
+    ) -> Result<Option<String>> {
+        let route = Pagination::url(topic_slug, page);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(Some(output));
+        }
+        let generation = self.cache.generation();
+
         let site = &self.app.site;
         let mut context = TemplateContext::new();
         context.insert("site", site);
+        if let Some(name) = self.topic_name(topic_slug) {
+            context.insert(
+                "topic",
+                &Topic {
+                    name,
+                    slug: topic_slug,
+                },
+            );
+        }
 
         if topic_slug == "gallery" {
             debug!("Rendering image gallery");
+            if page != 1 {
+                return Ok(None);
+            }
             // Need to make this an async call
             let gallery = self.load_gallery()?;
             context.insert("gallery", &gallery);
         } else {
-            debug!("Rendering topic: '{}'", topic_slug);
+            debug!("Rendering topic: '{}', page: {}", topic_slug, page);
             // Need to make this an async call
             let topic_data = self.load_topic(topic_slug).await?;
-            context.insert("posts", &topic_data);
+            let Some((posts, pagination)) = self.paginate(topic_slug, topic_data, page) else {
+                debug!("Page {} is out of range for topic: '{}'", page, topic_slug);
+                return Ok(None);
+            };
+            insert_posts(&mut context, &posts);
+            context.insert("pagination", &pagination);
         }
 
         let output = self
@@ -98,36 +216,91 @@ impl Engine {
             })?;
 
         trace!("Rendered content for topic: {}\n{}", topic_slug, output);
-        Ok(output)
+        self.cache.insert(route, output.clone(), generation);
+        Ok(Some(output))
+    }
+
+    /// Splits a topic's posts into the requested page, per `site.posts_per_page`.
+    ///
+    /// An empty topic still has a single (empty) first page.
+    fn paginate(
+        &self,
+        topic_slug: &str,
+        posts: Vec<Post>,
+        page: usize,
+    ) -> Option<(Vec<Post>, Pagination)> {
+        let per_page = match self.app.site.posts_per_page {
+            Some(n) if n > 0 => n,
+            _ => posts.len().max(1),
+        };
+        let total = posts.len().div_ceil(per_page).max(1);
+        if page == 0 || page > total {
+            return None;
+        }
+
+        let page_url = |n: usize| Pagination::url(topic_slug, n);
+        let pagination = Pagination {
+            current: page,
+            total,
+            prev: (page > 1).then(|| page_url(page - 1)),
+            next: (page < total).then(|| page_url(page + 1)),
+        };
+
+        let posts = posts
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+        Some((posts, pagination))
     }
 
-    async fn load_topic(&self, topic_slug: &str) -> Result<Vec<String>> {
+    /// Loads every post in a topic, ordered per [`Engine::sort_posts`].
+    pub(crate) async fn load_topic(&self, topic_slug: &str) -> Result<Vec<Post>> {
         trace!("Loading topic content for '{}'", topic_slug);
         let topic_path = Path::new(&self.app.docpaths.webroot)
             .join(topic_slug)
             .join("posts");
         let pat = format!("{}/*.md", topic_path.display());
         let paths = common::path_matches(&pat)?;
-        Self::read_all_to_html(paths).await
+        let mut posts = self.read_all_to_html(paths).await?;
+        posts.retain_mut(|post| self.check_published(post));
+        self.sort_posts(topic_slug, &mut posts);
+        Ok(posts)
     }
 
-    async fn read_all_to_html(paths: Vec<PathBuf>) -> Result<Vec<String>> {
+    /// Reads every post in `paths`, leaving out any that fail to read or parse.
+    ///
+    /// A single bad post is logged rather than failing every page and feed that lists it, and
+    /// remains an error when requested on its own.
+    async fn read_all_to_html(&self, paths: Vec<PathBuf>) -> Result<Vec<Post>> {
         debug!("Rendering Topic Markdown to HTML");
-        let mut contents: Vec<String> = Vec::new();
+        let mut contents: Vec<Post> = Vec::new();
         for path in paths {
-            trace!("Rendering {} to HTML", &path.display());
-            let buf = tokio::fs::read_to_string(&path)
-                .await
-                .with_context(|| format!("failure reading '{}' to string", &path.display()))?;
-            let parser = Parser::new(&buf);
-            let mut html_output = String::new();
-            html::push_html(&mut html_output, parser);
-            contents.push(html_output);
+            match self.read_post_to_html(&path).await {
+                Ok(post) => contents.push(post),
+                Err(err) => error!("Leaving out '{}': {:#}", path.display(), err),
+            }
         }
 
         Ok(contents)
     }
 
+    /// Orders posts newest-first according to the topic's [`PostOrder`].
+    ///
+    /// Posts sharing a publish date fall back to reverse-lexical filename order.
+    fn sort_posts(&self, topic_slug: &str, posts: &mut [Post]) {
+        match self.app.site.order_for(topic_slug) {
+            PostOrder::Date => {
+                trace!("Sorting '{}' posts by publish date", topic_slug);
+                posts.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.path.cmp(&a.path)))
+            }
+            PostOrder::Filename => {
+                trace!("Sorting '{}' posts by filename", topic_slug);
+                posts.sort_by(|a, b| b.path.cmp(&a.path))
+            }
+        }
+    }
+
     fn load_gallery(&self) -> Result<Vec<PathBuf>> {
         debug!("Loading gallery content");
         let gallery_path = Path::new(&self.app.docpaths.webroot)
@@ -147,12 +320,20 @@ impl Engine {
 
     /// Renders `/:topic/posts/:post` content as HTML
     pub(crate) async fn render_post(&self, topic_slug: &str, post: &str) -> Result<String> {
+        let route = format!("/{}/posts/{}", topic_slug, post);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(output);
+        }
+        let generation = self.cache.generation();
+
         debug!("Rendering post: '{}'", post);
         let site = &self.app.site;
         let post_data = self.load_post(topic_slug, post).await?;
         let mut context = TemplateContext::new();
         context.insert("site", site);
-        context.insert("post", &post_data);
+        context.insert("post", &post_data.html);
+        context.insert("meta", &post_data.meta);
+        context.insert("post_meta", &post_data);
         let output = self
             .instance
             .render(&site.template, &context)
@@ -164,33 +345,62 @@ impl Engine {
             })?;
 
         trace!("Rendered content for post: {}\n{}", topic_slug, output);
+        self.cache.insert(route, output.clone(), generation);
         Ok(output)
     }
 
-    async fn load_post(&self, topic_slug: &str, post: &str) -> Result<String> {
+    async fn load_post(&self, topic_slug: &str, post: &str) -> Result<Post> {
         trace!("Loading post content for '{}'", post);
         let topic_path = Path::new(&self.app.docpaths.webroot)
             .join(topic_slug)
             .join("posts");
         let post_path = format!("{}/{}.md", topic_path.display(), post);
-        Self::read_post_to_html(post_path).await
+        let mut post_data = self.read_post_to_html(&post_path).await?;
+        if !self.check_published(&mut post_data) {
+            return Err(anyhow!("'{}' is not published", post_path));
+        }
+        Ok(post_data)
     }
 
-    async fn read_post_to_html<P: AsRef<Path>>(path: P) -> Result<String> {
+    /// Marks drafts and posts scheduled for later, returning whether `post` may be shown.
+    ///
+    /// The render cache expires when a scheduled post becomes due, so it appears without a restart.
+    fn check_published(&self, post: &mut Post) -> bool {
+        if let Some(publish_date) = post.meta.publish_date.filter(|date| *date > Utc::now()) {
+            trace!(
+                "'{}' is scheduled for {}",
+                post.path.display(),
+                publish_date
+            );
+            self.cache.expire_at(publish_date);
+            post.draft = true;
+        }
+        post.draft |= post.meta.draft;
+        self.drafts || !post.draft
+    }
+
+    async fn read_post_to_html<P: AsRef<Path>>(&self, path: P) -> Result<Post> {
         debug!("Rendering Post Markdown to HTML");
         trace!("Rendering {} to HTML", &path.as_ref().display());
-        let buf = tokio::fs::read_to_string(&path)
+        let mut f = File::open(&path)
+            .await
+            .with_context(|| format!("failed to open '{}'", &path.as_ref().display()))?;
+        let modified: DateTime<Utc> = f.metadata().await?.modified()?.into();
+        let mut buf = String::new();
+        f.read_to_string(&mut buf)
             .await
             .with_context(|| format!("failure reading '{}' to string", &path.as_ref().display()))?;
-        let parser = Parser::new(&buf);
-        let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
-
-        Ok(html_output)
+        Post::parse(&path, &buf, modified, self.app.site.excerpt_paragraphs())
+            .with_context(|| format!("failure parsing '{}'", &path.as_ref().display()))
     }
 
     /// Renders `/rss.xml` for all topics
     pub(crate) async fn rss(&self) -> Result<String> {
+        if let Some(output) = self.cache.get("/rss.xml") {
+            return Ok(output);
+        }
+        let generation = self.cache.generation();
+
         debug!("Rendering RSS Feed");
         let site = &self.app.site;
         let items = Self::rss_items(self).await?;
@@ -200,83 +410,399 @@ impl Engine {
         channel.set_description(format!("{} RSS Feed", &site.name));
         channel.set_items(items);
 
-        Ok(channel.to_string())
+        let output = channel.to_string();
+        self.cache
+            .insert("/rss.xml".to_owned(), output.clone(), generation);
+        Ok(output)
+    }
+
+    /// Renders `/:topic/rss.xml` for a single topic
+    ///
+    /// Returns `None` when `topic_slug` is not one of the site's topics.
+    pub(crate) async fn topic_rss(&self, topic_slug: &str) -> Result<Option<String>> {
+        let route = format!("/{}/rss.xml", topic_slug);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(Some(output));
+        }
+        let generation = self.cache.generation();
+        let Some(name) = self.topic_name(topic_slug) else {
+            return Ok(None);
+        };
+
+        debug!("Rendering RSS Feed for topic: '{}'", topic_slug);
+        let site = &self.app.site;
+        let title = format!("{} - {}", &site.name, name);
+        let mut channel = Channel::default();
+        channel.set_link(format!("{}/{}", site.url.trim_end_matches('/'), topic_slug));
+        channel.set_description(format!("{} RSS Feed", &title));
+        channel.set_title(title);
+        channel.set_items(self.topic_to_item(topic_slug).await?);
+
+        let output = channel.to_string();
+        self.cache.insert(route, output.clone(), generation);
+        Ok(Some(output))
     }
 
     async fn rss_items(&self) -> Result<Vec<Item>> {
         debug!("Building RSS Items");
-        let mut items: Vec<Item> = Vec::new();
-        items.append(&mut Self::topic_to_item(self, "main").await?);
-
-        for topic in &self.app.site.topics {
-            let mut topic_items = Self::topic_to_item(self, &common::slugify(topic)).await?;
-            items.append(&mut topic_items);
-        }
+        let items = self
+            .feed_posts()
+            .await?
+            .into_iter()
+            .map(|post| self.post_to_item(post))
+            .collect();
 
         Ok(items)
     }
 
     async fn topic_to_item(&self, topic_slug: &str) -> Result<Vec<Item>> {
         trace!("Generating RSS Items for topic: {}", &topic_slug);
-        let mut items: Vec<Item> = Vec::new();
-        let topic_path = Path::new(&self.app.docpaths.webroot)
-            .join(topic_slug)
-            .join("posts");
-        let pat = format!("{}/*.md", topic_path.display());
-        let paths = common::path_matches(&pat)?;
-        for path in paths {
-            trace!("Generating RSS Item for post at: {}", path.display());
-            let link = format!(
-                "{}/{}/{}",
-                &self.app.site.url,
-                path.strip_prefix(&self.app.docpaths.webroot)?
-                    .parent()
-                    .unwrap()
-                    .to_str()
-                    .unwrap(),
-                path.file_stem().unwrap().to_str().unwrap()
-            );
-            let f = File::open(&path).await?;
-            let updated: DateTime<Utc> = f.metadata().await?.modified()?.into();
+        // Feeds run newest-first even for topics ordered by filename.
+        let mut posts = self.load_topic(topic_slug).await?;
+        posts.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.path.cmp(&a.path)));
+        let items = self
+            .limit_feed(posts)
+            .into_iter()
+            .map(|post| self.post_to_item(post))
+            .collect();
 
-            let updated = updated.to_rfc2822();
+        Ok(items)
+    }
 
-            let description = Self::read_post_to_html(path).await?;
+    fn post_to_item(&self, post: Post) -> Item {
+        trace!("Generating RSS Item for post at: {}", post.path.display());
+        let permalink = self.permalink(&post);
+        let mut guid = Guid::default();
+        guid.set_value(permalink.clone());
+        guid.set_permalink(true);
+        let mut category = Category::default();
+        category.set_name(self.topic_name(&post.topic).unwrap_or(&post.topic));
+
+        let mut item = Item::default();
+        item.set_title(post.title.clone());
+        item.set_link(permalink);
+        item.set_guid(guid);
+        item.set_categories(vec![category]);
+        item.set_pub_date(post.date.to_rfc2822());
+        item.set_description(self.feed_html(&post));
+        item
+    }
 
-            let mut item = Item::default();
-            item.set_link(link);
-            item.set_pub_date(updated);
-            item.set_description(description.to_owned());
-            items.push(item);
+    /// Renders `/atom.xml` for all topics
+    pub(crate) async fn atom(&self) -> Result<String> {
+        if let Some(output) = self.cache.get("/atom.xml") {
+            return Ok(output);
         }
+        let generation = self.cache.generation();
 
-        Ok(items)
+        debug!("Rendering Atom Feed");
+        let site = &self.app.site;
+        let entries = self.atom_entries().await?;
+        let updated = entries
+            .iter()
+            .map(|entry| *entry.updated())
+            .max()
+            .unwrap_or_else(|| Utc::now().into());
+
+        let mut link = atom_syndication::Link::default();
+        link.set_href(&site.url);
+        let mut feed = atom_syndication::Feed::default();
+        feed.set_title(site.name.as_str());
+        feed.set_id(&site.url);
+        feed.set_updated(updated);
+        feed.set_authors(vec![self.atom_author()]);
+        feed.set_links(vec![link]);
+        feed.set_entries(entries);
+
+        let output = feed.to_string();
+        self.cache
+            .insert("/atom.xml".to_owned(), output.clone(), generation);
+        Ok(output)
+    }
+
+    async fn atom_entries(&self) -> Result<Vec<atom_syndication::Entry>> {
+        debug!("Building Atom Entries");
+        let entries = self
+            .feed_posts()
+            .await?
+            .into_iter()
+            .map(|post| {
+                trace!("Generating Atom Entry for post at: {}", post.path.display());
+                let permalink = self.permalink(&post);
+                let mut link = atom_syndication::Link::default();
+                link.set_href(&permalink);
+                let mut content = atom_syndication::Content::default();
+                content.set_content_type("html".to_owned());
+                content.set_value(self.feed_html(&post));
+
+                let mut entry = atom_syndication::Entry::default();
+                entry.set_title(post.title.unwrap_or(post.slug));
+                entry.set_id(permalink);
+                entry.set_updated(post.updated);
+                entry.set_published(Some(post.date.into()));
+                entry.set_authors(vec![self.atom_author()]);
+                entry.set_links(vec![link]);
+                entry.set_content(content);
+                entry
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Renders `/feed.json` for all topics
+    pub(crate) async fn json_feed(&self) -> Result<String> {
+        if let Some(output) = self.cache.get("/feed.json") {
+            return Ok(output);
+        }
+        let generation = self.cache.generation();
+
+        debug!("Rendering JSON Feed");
+        let site = &self.app.site;
+        let mut feed = JsonFeed::new(&site.name, &site.url, &site.author);
+        feed.items = self
+            .feed_posts()
+            .await?
+            .into_iter()
+            .map(|post| {
+                trace!(
+                    "Generating JSON Feed item for post at: {}",
+                    post.path.display()
+                );
+                let permalink = self.permalink(&post);
+                let content_html = self.feed_html(&post);
+                JsonItem {
+                    id: permalink.clone(),
+                    url: permalink,
+                    title: post.title,
+                    content_html,
+                    summary: post.meta.summary,
+                    date_published: post.date,
+                    date_modified: post.updated,
+                    authors: post
+                        .meta
+                        .author
+                        .map(|name| vec![Author { name }])
+                        .unwrap_or_default(),
+                    tags: post.meta.tags,
+                }
+            })
+            .collect();
+
+        let output =
+            serde_json::to_string_pretty(&feed).context("failure serializing JSON Feed")?;
+        self.cache
+            .insert("/feed.json".to_owned(), output.clone(), generation);
+        Ok(output)
+    }
+
+    fn atom_author(&self) -> atom_syndication::Person {
+        let mut author = atom_syndication::Person::default();
+        author.set_name(&self.app.site.author);
+        author
+    }
+
+    /// Loads the posts of `main` and every topic, newest first regardless of topic ordering.
+    async fn all_posts(&self) -> Result<Vec<Post>> {
+        let mut posts = self.load_topic("main").await?;
+        for topic_slug in &self.topic_slugs {
+            trace!("Loading posts for topic: {}", topic_slug);
+            posts.append(&mut self.load_topic(topic_slug).await?);
+        }
+
+        posts.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.path.cmp(&a.path)));
+        Ok(posts)
+    }
+
+    /// Loads the posts syndicated by the site's feeds from `main` and every topic.
+    async fn feed_posts(&self) -> Result<Vec<Post>> {
+        let posts = self.all_posts().await?;
+        Ok(self.limit_feed(posts))
+    }
+
+    /// Keeps at most `feed.max_items` of `posts`, which must already be ordered newest-first.
+    fn limit_feed(&self, mut posts: Vec<Post>) -> Vec<Post> {
+        if let Some(max_items) = self.app.feed.max_items {
+            posts.truncate(max_items);
+        }
+        posts
+    }
+
+    /// Returns the HTML syndicated for a post: its excerpt when `feed.excerpt_only` is set.
+    fn feed_html(&self, post: &Post) -> String {
+        if self.app.feed.excerpt_only {
+            post.excerpt.clone()
+        } else {
+            post.html.clone()
+        }
+    }
+
+    /// Renders `/sitemap.xml`, listing the index, every topic, and every post
+    ///
+    /// Sites with more than [`sitemap::MAX_URLS`] URLs get a sitemap index instead, referencing
+    /// each part served by [`Engine::sitemap_part`].
+    pub(crate) async fn sitemap(&self) -> Result<String> {
+        if let Some(output) = self.cache.get("/sitemap.xml") {
+            return Ok(output);
+        }
+        let generation = self.cache.generation();
+
+        debug!("Rendering sitemap");
+        let urls = self.sitemap_urls().await?;
+        let output = if urls.len() > sitemap::MAX_URLS {
+            sitemap::index(&self.app.site.url, urls.chunks(sitemap::MAX_URLS))
+        } else {
+            sitemap::urlset(&urls)
+        };
+
+        self.cache
+            .insert("/sitemap.xml".to_owned(), output.clone(), generation);
+        Ok(output)
+    }
+
+    /// Renders `/sitemap/{part}.xml`, with parts numbered from `1`
+    ///
+    /// Returns `None` when `/sitemap.xml` is not an index, or `part` is beyond the last part.
+    pub(crate) async fn sitemap_part(&self, part: usize) -> Result<Option<String>> {
+        let route = format!("/sitemap/{}.xml", part);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(Some(output));
+        }
+        let generation = self.cache.generation();
+
+        let urls = self.sitemap_urls().await?;
+        if urls.len() <= sitemap::MAX_URLS || part == 0 {
+            return Ok(None);
+        }
+        let Some(chunk) = urls.chunks(sitemap::MAX_URLS).nth(part - 1) else {
+            return Ok(None);
+        };
+
+        debug!("Rendering sitemap part: {}", part);
+        let output = sitemap::urlset(chunk);
+        self.cache.insert(route, output.clone(), generation);
+        Ok(Some(output))
+    }
+
+    async fn sitemap_urls(&self) -> Result<Vec<SitemapUrl>> {
+        let base = self.app.site.url.trim_end_matches('/');
+        let mut urls = Vec::new();
+        for topic_slug in std::iter::once("main").chain(self.topic_slugs.iter().map(String::as_str))
+        {
+            trace!("Building sitemap URLs for topic: {}", topic_slug);
+            let posts = self.load_topic(topic_slug).await?;
+            urls.push(SitemapUrl {
+                loc: format!("{}{}", base, Pagination::url(topic_slug, 1)),
+                lastmod: posts.iter().map(|post| post.updated).max(),
+            });
+            urls.extend(posts.iter().map(|post| SitemapUrl {
+                loc: self.permalink(post),
+                lastmod: Some(post.updated),
+            }));
+        }
+
+        let tags = self.tags().await?;
+        if !tags.is_empty() {
+            urls.push(SitemapUrl {
+                loc: format!("{}/tags", base),
+                lastmod: tags.iter().map(|tag| tag.updated).max(),
+            });
+        }
+        urls.extend(tags.into_iter().map(|tag| SitemapUrl {
+            loc: format!("{}{}", base, tag.url),
+            lastmod: Some(tag.updated),
+        }));
+
+        Ok(urls)
+    }
+
+    /// Renders `/robots.txt` from the `[robots]` configuration
+    pub(crate) fn robots(&self) -> String {
+        let robots = &self.app.robots;
+        let mut output = format!("User-agent: {}\n", robots.user_agent);
+        for path in &robots.allow {
+            output.push_str(&format!("Allow: {}\n", path));
+        }
+        for path in &robots.disallow {
+            output.push_str(&format!("Disallow: {}\n", path));
+        }
+        if robots.allow.is_empty() && robots.disallow.is_empty() {
+            output.push_str("Disallow:\n");
+        }
+        if robots.sitemap {
+            output.push_str(&format!(
+                "\nSitemap: {}/sitemap.xml\n",
+                self.app.site.url.trim_end_matches('/')
+            ));
+        }
+        output
+    }
+
+    /// Returns the configured name of the topic with `topic_slug`, if any.
+    fn topic_name(&self, topic_slug: &str) -> Option<&str> {
+        self.topic_slugs
+            .iter()
+            .position(|slug| slug == topic_slug)
+            .map(|idx| self.app.site.topics[idx].as_str())
     }
+
+    /// Returns the absolute URL of a post, which also serves as its stable feed identifier.
+    fn permalink(&self, post: &Post) -> String {
+        format!("{}{}", self.app.site.url.trim_end_matches('/'), post.url)
+    }
+}
+
+/// Inserts a list of posts into `context` as `posts`, holding only each post's HTML as in
+/// templates written before front matter, and as `posts_meta`, holding each whole [`Post`].
+fn insert_posts(context: &mut TemplateContext, posts: &[Post]) {
+    let html: Vec<&str> = posts.iter().map(|post| post.html.as_str()).collect();
+    context.insert("posts", &html);
+    context.insert("posts_meta", posts);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing;
     use std::fs::File;
     use std::io::prelude::*;
 
     #[test]
     fn check_default_template() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
         let tera = Engine::load_template(&config);
         assert!(tera.is_ok())
     }
 
+    #[tokio::test]
+    async fn check_default_template_escapes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let post = "+++\ntitle = \"Fish & <Chips>\"\ndate = 2024-05-01\ntags = [\"<b>Bold</b>\"]\n+++\nFish for dinner\n";
+        std::fs::write(dir.path().join("site/webroot/one/posts/fish.md"), post).unwrap();
+        let engine = Engine::new(config).unwrap();
+
+        let title = "Fish &amp; &lt;Chips&gt;";
+        let tag = "&lt;b&gt;Bold&lt;&#x2F;b&gt;";
+        let page = engine.render_post("one", "fish").await.unwrap();
+        assert!(page.contains(tag));
+        let page = engine.render_tags().await.unwrap();
+        assert!(page.contains(tag));
+        assert!(!page.contains("<b>Bold</b>"));
+        let page = engine.render_archive(None, None).await.unwrap().unwrap();
+        assert!(page.contains(title));
+        let page = engine.render_search("fish").await.unwrap();
+        assert!(page.contains(title));
+        assert!(page.contains("<mark>Fish</mark>"));
+        assert!(!page.contains("<Chips>"));
+    }
+
     #[tokio::test]
     async fn check_render_post() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(config);
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
 
         let post = r#"
 ### Something
@@ -302,13 +828,120 @@ Super Wow!
         assert!(page2.contains("Super Wow!"));
     }
 
+    #[tokio::test]
+    async fn check_render_post_meta() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One", "Gallery"]);
+        config.site.template = "meta.tmpl".to_owned();
+
+        let template = r#"{% if post %}{{ meta.title }} by {{ post_meta.meta.author }}{{ post }}{% else %}{% for post in posts_meta %}[{{ post.meta.title | default(value="untitled") }}]{% endfor %}{% endif %}"#;
+        let mut f = File::create(dir.path().join("site/templates/meta.tmpl")).unwrap();
+        f.write_all(template.as_bytes()).unwrap();
+
+        let post = r#"+++
+title = "Front Matter"
+author = "Someone Else"
++++
+Body text
+"#;
+        let post2 = r#"---
+title: YAML Matter
+---
+Other text
+"#;
+        let plain = "Plain text\n";
+
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/post1.md")).unwrap();
+        f.write_all(post.as_bytes()).unwrap();
+
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/post2.md")).unwrap();
+        f.write_all(post2.as_bytes()).unwrap();
+
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/post3.md")).unwrap();
+        f.write_all(plain.as_bytes()).unwrap();
+
+        let engine = Engine::new(config).unwrap();
+        let page = engine.render_post("one", "post1").await.unwrap();
+        assert_eq!(page, "Front Matter by Someone Else<p>Body text</p>\n");
+
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert_eq!(page, "[untitled][YAML Matter][Front Matter]");
+
+        // A post with broken front matter is left out of lists, but still fails on its own.
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/post4.md")).unwrap();
+        f.write_all(b"+++\ntitle = \n+++\nBroken\n").unwrap();
+        engine.cache.clear();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert_eq!(page, "[untitled][YAML Matter][Front Matter]");
+        assert!(engine.rss().await.is_ok());
+        assert!(engine.render_post("one", "post4").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn check_drafts_and_scheduled() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let now = Utc::now();
+        let soon =
+            (now + chrono::Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let recent =
+            (now - chrono::Duration::minutes(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let files = [
+            ("live.md", "# Live Post\n".to_owned()),
+            (
+                "draft.md",
+                "+++\ndraft = true\n+++\n# Draft Post\n".to_owned(),
+            ),
+            (
+                "later.md",
+                "+++\npublish_date = 2999-01-01\n+++\n# Later Post\n".to_owned(),
+            ),
+            (
+                "soon.md",
+                format!("+++\npublish_date = \"{}\"\n+++\n# Soon Post\n", soon),
+            ),
+            (
+                "recent.md",
+                format!("+++\npublish_date = \"{}\"\n+++\n# Recent Post\n", recent),
+            ),
+        ];
+        for (name, content) in files {
+            let path = dir.path().join("site/webroot/one/posts").join(name);
+            std::fs::write(path, content).unwrap();
+        }
+
+        let mut engine = Engine::new(config).unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert!(page.contains("Live Post"));
+        assert!(page.contains("Recent Post"));
+        assert!(!page.contains("Draft Post"));
+        assert!(!page.contains("Later Post"));
+        assert!(!page.contains("Soon Post"));
+        assert!(!page.contains("DRAFT"));
+        assert_eq!(engine.rss_items().await.unwrap().len(), 2);
+        assert!(engine.render_post("one", "draft").await.is_err());
+        assert!(engine.render_post("one", "later").await.is_err());
+        assert!(engine.render_post("one", "soon").await.is_err());
+        assert!(engine.render_post("one", "recent").await.is_ok());
+
+        // The cache is dropped when the earliest scheduled post becomes due.
+        let due = DateTime::parse_from_rfc3339(&soon).unwrap();
+        assert_eq!(engine.cache.expiry(), Some(due.with_timezone(&Utc)));
+
+        engine.drafts = true;
+        engine.cache.clear();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert!(page.contains("Draft Post"));
+        assert!(page.contains("Later Post"));
+        let post = engine.render_post("one", "draft").await.unwrap();
+        assert!(post.contains("DRAFT"));
+    }
+
     #[tokio::test]
     async fn check_render_topic() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(config);
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
 
         let post = r#"
 ### Something
@@ -327,21 +960,141 @@ Super Wow!
         let mut f = File::create(dir.path().join("site/webroot/one/posts/post2.md")).unwrap();
         f.write_all(post2.as_bytes()).unwrap();
 
-        let page = engine.render_topic("one").await.unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
 
         assert!(page.contains("super useful"));
         assert!(page.contains("Super Wow!"));
     }
 
+    #[tokio::test]
+    async fn check_topic_ordering() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One", "Gallery"]);
+        config.site.template = "order.tmpl".to_owned();
+
+        let template = r#"{% for post in posts %}{{ post | striptags | trim }},{% endfor %}"#;
+        let mut f = File::create(dir.path().join("site/templates/order.tmpl")).unwrap();
+        f.write_all(template.as_bytes()).unwrap();
+
+        let posts = [
+            ("2024-01-01-a.md", "A"),
+            ("2024-03-01-b.md", "B"),
+            ("zzz.md", "+++\ndate = 2024-02-01\n+++\nZ"),
+            ("yyy.md", "+++\ndate = 2024-02-01\n+++\nY"),
+        ];
+        for (name, body) in posts {
+            let mut f = File::create(dir.path().join("site/webroot/one/posts").join(name)).unwrap();
+            f.write_all(body.as_bytes()).unwrap();
+        }
+
+        let engine = Engine::new(config).unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert_eq!(page, "B,Z,Y,A,");
+
+        let mut config = AppConfig::from_path(dir.path().join("config.toml")).unwrap();
+        config.site.template = "order.tmpl".to_owned();
+        config
+            .site
+            .ordering
+            .insert("One".to_owned(), PostOrder::Filename);
+        let engine = Engine::new(config).unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert_eq!(page, "Z,Y,B,A,");
+    }
+
+    #[tokio::test]
+    async fn check_topic_pagination() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One", "Gallery"]);
+        config.site.posts_per_page = Some(2);
+
+        for n in 1..=5 {
+            let mut f =
+                File::create(dir.path().join(format!("site/webroot/one/posts/{}.md", n))).unwrap();
+            f.write_all(format!("Post number {}\n", n).as_bytes())
+                .unwrap();
+        }
+
+        let engine = Engine::new(config).unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert!(page.contains("Post number 5"));
+        assert!(page.contains("Post number 4"));
+        assert!(!page.contains("Post number 3"));
+        assert!(page.contains("Page 1 of 3"));
+        assert!(page.contains(r#"href="/one/page/2""#));
+        assert!(!page.contains("Newer"));
+
+        let page = engine.render_topic("one", 3).await.unwrap().unwrap();
+        assert!(page.contains("Post number 1"));
+        assert!(page.contains(r#"href="/one/page/2""#));
+        assert!(!page.contains("Older"));
+
+        assert!(engine.render_topic("one", 0).await.unwrap().is_none());
+        assert!(engine.render_topic("one", 4).await.unwrap().is_none());
+        assert!(engine.render_topic("main", 2).await.unwrap().is_none());
+        assert!(engine.render_topic("main", 1).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn check_topic_excerpts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One", "Gallery"]);
+        config.site.excerpts = true;
+
+        let post = "### Long Post\n\nIntro text\n\nHidden text\n";
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/long.md")).unwrap();
+        f.write_all(post.as_bytes()).unwrap();
+
+        let post = "Short text\n";
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/short.md")).unwrap();
+        f.write_all(post.as_bytes()).unwrap();
+
+        let engine = Engine::new(config).unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert!(page.contains("Intro text"));
+        assert!(!page.contains("Hidden text"));
+        assert!(page.contains(r#"<a href="/one/posts/long">Read more of Long Post"#));
+        assert!(page.contains("Short text"));
+        assert!(!page.contains(r#"href="/one/posts/short""#));
+    }
+
+    #[tokio::test]
+    async fn check_render_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
+
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/1.md")).unwrap();
+        f.write_all(b"Original post\n").unwrap();
+
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        let post = engine.render_post("one", "1").await.unwrap();
+        let rss = engine.rss().await.unwrap();
+        assert!(page.contains("Original post"));
+
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/1.md")).unwrap();
+        f.write_all(b"Updated post\n").unwrap();
+
+        assert_eq!(page, engine.render_topic("one", 1).await.unwrap().unwrap());
+        assert_eq!(post, engine.render_post("one", "1").await.unwrap());
+        assert_eq!(rss, engine.rss().await.unwrap());
+
+        engine.cache.clear();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        let post = engine.render_post("one", "1").await.unwrap();
+        let rss = engine.rss().await.unwrap();
+        assert!(page.contains("Updated post"));
+        assert!(post.contains("Updated post"));
+        assert!(rss.contains("Updated post"));
+    }
+
     #[tokio::test]
     async fn check_render_empty_topic() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(config);
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
 
-        let page = engine.render_topic("one").await.unwrap();
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
 
         assert!(page.contains("Coming Soon"));
     }
@@ -349,10 +1102,8 @@ Super Wow!
     #[tokio::test]
     async fn check_render_gallery_topic() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(config);
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
 
         let fake_img = "some bytes";
         let fake_img_2 = "some more bytes";
@@ -362,7 +1113,7 @@ Super Wow!
 
         let mut f = File::create(dir.path().join("site/webroot/gallery/ext/1.jpg")).unwrap();
         f.write_all(fake_img_2.as_bytes()).unwrap();
-        let page = engine.render_topic("gallery").await.unwrap();
+        let page = engine.render_topic("gallery", 1).await.unwrap().unwrap();
 
         assert!(page.contains("<script>"));
     }
@@ -370,12 +1121,10 @@ Super Wow!
     #[tokio::test]
     async fn check_render_empty_gallery() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(config);
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
 
-        let page = engine.render_topic("gallery").await.unwrap();
+        let page = engine.render_topic("gallery", 1).await.unwrap().unwrap();
 
         assert!(page.contains("Coming Soon"));
     }
@@ -383,10 +1132,8 @@ Super Wow!
     #[tokio::test]
     async fn check_render_rss() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] =
-            b"Site Name\nAuthor Name\nhttps://special.example.site\nOne, Gallery\nadmin\n";
-        let config = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(config);
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
 
         let main_post = r#"
 ### The Main Page
@@ -418,5 +1165,199 @@ Super Wow TWICE!
         assert!(rss.contains("The Main Page"));
         assert!(rss.contains("Super Wow!"));
         assert!(rss.contains("A second post in One"));
+
+        let topic_rss = engine.topic_rss("one").await.unwrap().unwrap();
+        let channel: Channel = topic_rss.parse().unwrap();
+        assert_eq!(channel.title(), "Site Name - One");
+        assert_eq!(channel.link(), "https://special.example.site/one");
+        assert_eq!(channel.items().len(), 2);
+        assert!(!topic_rss.contains("The Main Page"));
+        assert!(engine.topic_rss("main").await.unwrap().is_none());
+        assert!(engine.topic_rss("nope").await.unwrap().is_none());
+
+        let page = engine.render_topic("one", 1).await.unwrap().unwrap();
+        assert!(page.contains(r#"<link rel="alternate" type="application/rss+xml" title="Site Name - One" href="/one/rss.xml">"#));
+        let page = engine.render_topic("main", 1).await.unwrap().unwrap();
+        assert!(!page.contains("rel=\"alternate\""));
+    }
+
+    #[tokio::test]
+    async fn check_rss_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site(&dir, &["One", "Two"]);
+        config.feed.max_items = Some(3);
+        config.feed.excerpt_only = true;
+
+        let files = [
+            ("one/posts/2024-01-01-a.md", "# Oldest\n\nSkipped\n"),
+            ("two/posts/2024-03-01-b.md", "# Newest\n\nFirst\n\nSecond\n"),
+            (
+                "one/posts/2024-02-01-c.md",
+                "+++\ntitle = \"Middle\"\n+++\nBody\n",
+            ),
+            ("main/posts/2023-12-01-d.md", "# Older\n\nBody\n"),
+            ("two/posts/2023-11-01-e.md", "No heading\n"),
+        ];
+        for (name, content) in files {
+            let mut f = File::create(dir.path().join("site/webroot").join(name)).unwrap();
+            f.write_all(content.as_bytes()).unwrap();
+        }
+
+        let engine = Engine::new(config).unwrap();
+        let channel: Channel = engine.rss().await.unwrap().parse().unwrap();
+        let titles: Vec<_> = channel.items().iter().map(|i| i.title()).collect();
+        assert_eq!(titles, [Some("Newest"), Some("Middle"), Some("Oldest")]);
+
+        let item = &channel.items()[0];
+        let guid = item.guid().unwrap();
+        assert_eq!(
+            guid.value(),
+            "https://special.example.site/two/posts/2024-03-01-b"
+        );
+        assert!(guid.is_permalink());
+        assert_eq!(item.categories()[0].name(), "Two");
+        assert_eq!(item.pub_date(), Some("Fri, 1 Mar 2024 00:00:00 +0000"));
+        assert!(item.description().unwrap().contains("First"));
+        assert!(!item.description().unwrap().contains("Second"));
+
+        let channel: Channel = engine
+            .topic_rss("two")
+            .await
+            .unwrap()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(channel.items().len(), 2);
+        assert_eq!(channel.items()[1].title(), None);
+    }
+
+    #[tokio::test]
+    async fn check_render_sitemap() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site_at(&dir, "https://special.example.site/", &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
+
+        let post = "+++\ndate = 2024-05-01\nupdated = 2024-06-01\n+++\nBody\n";
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/post.md")).unwrap();
+        f.write_all(post.as_bytes()).unwrap();
+
+        let sitemap = engine.sitemap().await.unwrap();
+        assert!(sitemap.contains("<urlset"));
+        assert!(sitemap.contains("<url><loc>https://special.example.site/</loc></url>"));
+        assert!(sitemap.contains(
+            "<url><loc>https://special.example.site/one</loc><lastmod>2024-06-01T00:00:00Z</lastmod></url>"
+        ));
+        assert!(sitemap.contains(
+            "<url><loc>https://special.example.site/one/posts/post</loc><lastmod>2024-06-01T00:00:00Z</lastmod></url>"
+        ));
+        assert!(sitemap.contains("<loc>https://special.example.site/gallery</loc>"));
+        assert!(engine.sitemap_part(1).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn check_render_robots() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config =
+            testing::site_at(&dir, "https://special.example.site/", &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
+        assert_eq!(
+            engine.robots(),
+            "User-agent: *\nDisallow:\n\nSitemap: https://special.example.site/sitemap.xml\n"
+        );
+
+        config = engine.app;
+        config.robots.user_agent = "ExampleBot".to_owned();
+        config.robots.allow = vec!["/one".to_owned()];
+        config.robots.disallow = vec!["/".to_owned()];
+        config.robots.sitemap = false;
+        let engine = Engine::new(config).unwrap();
+        assert_eq!(
+            engine.robots(),
+            "User-agent: ExampleBot\nAllow: /one\nDisallow: /\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn check_render_atom() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site_at(&dir, "https://special.example.site/", &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
+
+        let post = r#"+++
+title = "Atom Post"
+date = 2024-05-01
+updated = 2024-06-01T12:00:00Z
++++
+Atom body
+"#;
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/atom.md")).unwrap();
+        f.write_all(post.as_bytes()).unwrap();
+
+        let atom = engine.atom().await.unwrap();
+        let feed: atom_syndication::Feed = atom.parse().unwrap();
+        assert_eq!(feed.title().as_str(), "Site Name");
+        assert_eq!(feed.id(), "https://special.example.site/");
+        assert_eq!(feed.authors()[0].name(), "Author Name");
+        assert_eq!(feed.updated().to_rfc3339(), "2024-06-01T12:00:00+00:00");
+
+        let entry = &feed.entries()[0];
+        assert_eq!(entry.title().as_str(), "Atom Post");
+        assert_eq!(entry.id(), "https://special.example.site/one/posts/atom");
+        assert_eq!(entry.updated().to_rfc3339(), "2024-06-01T12:00:00+00:00");
+        assert_eq!(
+            entry.published().unwrap().to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+        assert_eq!(entry.authors()[0].name(), "Author Name");
+        assert!(entry
+            .content()
+            .unwrap()
+            .value()
+            .unwrap()
+            .contains("Atom body"));
+    }
+
+    #[tokio::test]
+    async fn check_render_json_feed() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = Engine::new(config).unwrap();
+
+        let post = r#"---
+date: 2024-05-01
+updated: 2024-06-01
+author: Guest Writer
+tags: [rust]
+---
+### JSON Post
+
+JSON body
+"#;
+        let mut f = File::create(dir.path().join("site/webroot/one/posts/json.md")).unwrap();
+        f.write_all(post.as_bytes()).unwrap();
+        let mut f = File::create(dir.path().join("site/webroot/main/posts/index.md")).unwrap();
+        f.write_all(b"Main body\n").unwrap();
+
+        let feed: serde_json::Value =
+            serde_json::from_str(&engine.json_feed().await.unwrap()).unwrap();
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(feed["title"], "Site Name");
+        assert_eq!(feed["home_page_url"], "https://special.example.site");
+        assert_eq!(feed["feed_url"], "https://special.example.site/feed.json");
+        assert_eq!(feed["authors"][0]["name"], "Author Name");
+
+        let items = feed["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].get("title").is_none());
+        assert_eq!(items[0]["content_html"], "<p>Main body</p>\n");
+        let item = &items[1];
+        assert_eq!(item["id"], "https://special.example.site/one/posts/json");
+        assert_eq!(item["url"], item["id"]);
+        assert_eq!(item["title"], "JSON Post");
+        assert_eq!(item["date_published"], "2024-05-01T00:00:00Z");
+        assert_eq!(item["date_modified"], "2024-06-01T00:00:00Z");
+        assert_eq!(item["authors"][0]["name"], "Guest Writer");
+        assert_eq!(item["tags"][0], "rust");
+        assert!(item["content_html"].as_str().unwrap().contains("JSON body"));
     }
 }
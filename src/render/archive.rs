@@ -0,0 +1,257 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides the chronological archive served from `/archive`, grouping posts from all topics by publish date.
+
+use chrono::{Datelike, Month};
+use log::{debug, trace};
+use serde::Serialize;
+use tera::Context as TemplateContext;
+
+use super::{Context, Result};
+use super::{Engine, Post};
+
+/// Posts grouped by year and month, exposed to the Tera context as `archive`.
+///
+/// `year` and `month` are set when the archive is limited to a single year or month.
+#[derive(Debug, Serialize)]
+pub(crate) struct Archive {
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub years: Vec<ArchiveYear>,
+}
+
+/// A year of an [`Archive`], newest first.
+#[derive(Debug, Serialize)]
+pub(crate) struct ArchiveYear {
+    pub year: i32,
+    pub url: String,
+    pub count: usize,
+    pub months: Vec<ArchiveMonth>,
+}
+
+/// A month of an [`ArchiveYear`], newest first.
+#[derive(Debug, Serialize)]
+pub(crate) struct ArchiveMonth {
+    pub month: u32,
+    /// English name of the month, such as `May`.
+    pub name: String,
+    pub url: String,
+    pub posts: Vec<Post>,
+}
+
+impl Archive {
+    /// Returns the URL path of the archive, optionally limited to a year or month.
+    pub(crate) fn url(year: Option<i32>, month: Option<u32>) -> String {
+        match (year, month) {
+            (Some(year), Some(month)) => format!("/archive/{}/{:02}", year, month),
+            (Some(year), None) => format!("/archive/{}", year),
+            _ => "/archive".to_owned(),
+        }
+    }
+
+    /// Groups `posts`, which must be ordered newest first, by year and month.
+    fn new(year: Option<i32>, month: Option<u32>, posts: &[Post]) -> Archive {
+        let mut years: Vec<ArchiveYear> = Vec::new();
+        for post in posts {
+            let (post_year, post_month) = (post.date.year(), post.date.month());
+            if year.is_some_and(|y| y != post_year) || month.is_some_and(|m| m != post_month) {
+                continue;
+            }
+
+            if years.last().is_none_or(|y| y.year != post_year) {
+                years.push(ArchiveYear {
+                    year: post_year,
+                    url: Archive::url(Some(post_year), None),
+                    count: 0,
+                    months: Vec::new(),
+                });
+            }
+            let archive_year = years.last_mut().unwrap();
+            archive_year.count += 1;
+
+            if archive_year
+                .months
+                .last()
+                .is_none_or(|m| m.month != post_month)
+            {
+                archive_year.months.push(ArchiveMonth {
+                    month: post_month,
+                    name: Month::try_from(post_month as u8)
+                        .map(|m| m.name().to_owned())
+                        .unwrap_or_default(),
+                    url: Archive::url(Some(post_year), Some(post_month)),
+                    posts: Vec::new(),
+                });
+            }
+            archive_year
+                .months
+                .last_mut()
+                .unwrap()
+                .posts
+                .push(post.clone());
+        }
+
+        Archive { year, month, years }
+    }
+}
+
+impl Engine {
+    /// Builds the [`Archive`] of posts from `main` and every topic, optionally limited to a year or month.
+    ///
+    /// Returns `None` when limited to a year or month without any posts.
+    pub(crate) async fn archive(
+        &self,
+        year: Option<i32>,
+        month: Option<u32>,
+    ) -> Result<Option<Archive>> {
+        let archive = Archive::new(year, month, &self.all_posts().await?);
+        if archive.years.is_empty() && year.is_some() {
+            return Ok(None);
+        }
+        Ok(Some(archive))
+    }
+
+    /// Renders `/archive`, `/archive/:year`, or `/archive/:year/:month`
+    ///
+    /// Returns `None` when limited to a year or month without any posts.
+    pub(crate) async fn render_archive(
+        &self,
+        year: Option<i32>,
+        month: Option<u32>,
+    ) -> Result<Option<String>> {
+        let route = Archive::url(year, month);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(Some(output));
+        }
+        let generation = self.cache.generation();
+        let Some(archive) = self.archive(year, month).await? else {
+            return Ok(None);
+        };
+
+        let output = self.render_archive_page(&archive)?;
+        self.cache.insert(route, output.clone(), generation);
+        Ok(Some(output))
+    }
+
+    /// Renders `/archive` and every year and month under it, returning each route with its output.
+    ///
+    /// Posts are read once for all pages, and the render cache is bypassed.
+    pub(crate) async fn render_archive_pages(&self) -> Result<Vec<(String, String)>> {
+        let posts = self.all_posts().await?;
+        let archive = Archive::new(None, None, &posts);
+        let mut routes = vec![(None, None)];
+        for year in &archive.years {
+            routes.push((Some(year.year), None));
+            routes.extend(year.months.iter().map(|m| (Some(year.year), Some(m.month))));
+        }
+
+        routes
+            .into_iter()
+            .map(|(year, month)| {
+                let output = self.render_archive_page(&Archive::new(year, month, &posts))?;
+                Ok((Archive::url(year, month), output))
+            })
+            .collect()
+    }
+
+    /// Renders an [`Archive`] that has already been built.
+    fn render_archive_page(&self, archive: &Archive) -> Result<String> {
+        let route = Archive::url(archive.year, archive.month);
+        debug!("Rendering archive: '{}'", route);
+        let site = &self.app.site;
+        let mut context = TemplateContext::new();
+        context.insert("site", site);
+        context.insert("archive", &archive);
+
+        let output = self
+            .instance
+            .render(&site.template, &context)
+            .with_context(|| {
+                format!(
+                    "failed rendering archive: {}, with Tera instance: {:?}",
+                    route, self.instance
+                )
+            })?;
+
+        trace!("Rendered content for archive: {}\n{}", route, output);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn check_render_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Two"]);
+        let engine = Engine::new(config).unwrap();
+
+        let files = [
+            ("one/posts/2023-12-24-a.md", "# December Post\n"),
+            ("two/posts/2024-05-01-b.md", "# Early May Post\n"),
+            ("main/posts/2024-05-20-c.md", "# Late May Post\n"),
+            ("one/posts/2024-06-02-d.md", "# June Post\n"),
+        ];
+        for (name, content) in files {
+            let path = dir.path().join("site/webroot").join(name);
+            std::fs::write(path, content).unwrap();
+        }
+
+        let archive = engine.archive(None, None).await.unwrap().unwrap();
+        let years: Vec<_> = archive
+            .years
+            .iter()
+            .map(|y| (y.year, y.count, y.months.len()))
+            .collect();
+        assert_eq!(years, [(2024, 3, 2), (2023, 1, 1)]);
+        let may = &archive.years[0].months[1];
+        assert_eq!(
+            (may.name.as_str(), may.url.as_str()),
+            ("May", "/archive/2024/05")
+        );
+        let titles: Vec<_> = may.posts.iter().map(|p| p.title.as_deref()).collect();
+        assert_eq!(titles, [Some("Late May Post"), Some("Early May Post")]);
+
+        let page = engine.render_archive(None, None).await.unwrap().unwrap();
+        assert!(page.contains(r#"<a href="/archive/2023">2023</a>"#));
+        assert!(page.contains(r#"<a href="/two/posts/2024-05-01-b">Early May Post</a>"#));
+
+        let page = engine
+            .render_archive(Some(2024), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(page.contains("June Post"));
+        assert!(!page.contains("December Post"));
+
+        let page = engine
+            .render_archive(Some(2024), Some(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(page.contains("Early May Post"));
+        assert!(!page.contains("June Post"));
+
+        assert!(engine
+            .render_archive(Some(2022), None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(engine
+            .render_archive(Some(2024), Some(1))
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
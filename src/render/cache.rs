@@ -0,0 +1,180 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides an in-memory cache of rendered output keyed by route.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+
+use super::SearchIndex;
+
+/// Rendered HTML and feeds keyed by the route that serves them, and the site's [`SearchIndex`].
+///
+/// Entries live until [`RenderCache::clear`] is called, which is done by
+/// [`crate::watch`] whenever site content or templates change on disk, or
+/// until the time set by [`RenderCache::expire_at`] passes.
+///
+/// Each clear starts a new generation. Renderers read [`RenderCache::generation`] before
+/// rendering and pass it back when storing the output, so that output rendered from content
+/// that changed mid-render is not cached.
+#[derive(Debug, Default)]
+pub(crate) struct RenderCache {
+    entries: Mutex<HashMap<String, String>>,
+    expires: Mutex<Option<DateTime<Utc>>>,
+    search: Mutex<Option<Arc<SearchIndex>>>,
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl RenderCache {
+    /// Returns the cached output for `route`, if any.
+    pub(crate) fn get(&self, route: &str) -> Option<String> {
+        self.check_expiry();
+        let entry = self.entries.lock().unwrap().get(route).cloned();
+        if entry.is_some() {
+            let hits = self.hits.fetch_add(1, Ordering::Relaxed) + 1;
+            debug!(
+                "Render cache hit for '{}' (hits: {}, misses: {})",
+                route,
+                hits,
+                self.misses.load(Ordering::Relaxed)
+            );
+        } else {
+            let misses = self.misses.fetch_add(1, Ordering::Relaxed) + 1;
+            debug!(
+                "Render cache miss for '{}' (hits: {}, misses: {})",
+                route,
+                self.hits.load(Ordering::Relaxed),
+                misses
+            );
+        }
+
+        entry
+    }
+
+    /// Returns the current generation, to be passed to [`RenderCache::insert`] once rendered.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Stores the output rendered for `route`, unless the cache was cleared since `generation`.
+    pub(crate) fn insert(&self, route: String, output: String, generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if self.generation() == generation {
+            entries.insert(route, output);
+        } else {
+            debug!("Not caching stale render of '{}'", route);
+        }
+    }
+
+    /// Returns the cached search index, if any.
+    pub(crate) fn search_index(&self) -> Option<Arc<SearchIndex>> {
+        self.check_expiry();
+        self.search.lock().unwrap().clone()
+    }
+
+    /// Stores the search index built from the current content, unless the cache was cleared
+    /// since `generation`.
+    pub(crate) fn set_search_index(&self, index: Arc<SearchIndex>, generation: u64) {
+        let mut search = self.search.lock().unwrap();
+        if self.generation() == generation {
+            *search = Some(index);
+        } else {
+            debug!("Not caching stale search index");
+        }
+    }
+
+    /// Drops every cached entry once `at` has passed, unless an earlier expiry is already set.
+    pub(crate) fn expire_at(&self, at: DateTime<Utc>) {
+        let mut expires = self.expires.lock().unwrap();
+        if expires.is_none_or(|current| at < current) {
+            *expires = Some(at);
+        }
+    }
+
+    /// Returns when the cache is next due to expire, if ever.
+    pub(crate) fn expiry(&self) -> Option<DateTime<Utc>> {
+        *self.expires.lock().unwrap()
+    }
+
+    /// Drops every cached entry.
+    pub(crate) fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        let mut search = self.search.lock().unwrap();
+        debug!("Clearing {} render cache entries", entries.len());
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.clear();
+        search.take();
+    }
+
+    fn check_expiry(&self) {
+        let expired = {
+            let mut expires = self.expires.lock().unwrap();
+            let expired = expires.is_some_and(|at| at <= Utc::now());
+            if expired {
+                *expires = None;
+            }
+            expired
+        };
+        if expired {
+            debug!("Render cache expired");
+            self.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_hits_and_misses() {
+        let cache = RenderCache::default();
+        assert!(cache.get("/").is_none());
+        cache.insert("/".to_owned(), "index".to_owned(), cache.generation());
+        assert_eq!(cache.get("/").as_deref(), Some("index"));
+        cache.clear();
+        assert!(cache.get("/").is_none());
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn cache_expiry() {
+        let cache = RenderCache::default();
+        cache.insert("/".to_owned(), "index".to_owned(), cache.generation());
+        cache.expire_at(Utc::now() + chrono::Duration::hours(1));
+        assert!(cache.get("/").is_some());
+
+        cache.expire_at(Utc::now() - chrono::Duration::seconds(1));
+        assert!(cache.get("/").is_none());
+        cache.insert("/".to_owned(), "index".to_owned(), cache.generation());
+        assert!(cache.get("/").is_some());
+    }
+
+    #[test]
+    fn stale_renders_not_cached() {
+        let cache = RenderCache::default();
+        let generation = cache.generation();
+        cache.clear();
+        cache.insert("/".to_owned(), "stale".to_owned(), generation);
+        assert!(cache.get("/").is_none());
+        cache.set_search_index(Arc::new(SearchIndex::default()), generation);
+        assert!(cache.search_index().is_none());
+
+        cache.insert("/".to_owned(), "fresh".to_owned(), cache.generation());
+        assert_eq!(cache.get("/").as_deref(), Some("fresh"));
+    }
+}
@@ -16,17 +16,26 @@ pub(crate) const TEMPLATE: &str = r#"
 <meta charset="UTF-8">
 <meta name="viewport" content="width=device-width, initial-scale=1.0">
 <link rel="stylesheet" href="https://cdn.simplecss.org/simple.min.css">
-<title>{{ site.name }}</title>
+<title>{{ site.name | escape }}</title>
+{%- if topic %}
+<link rel="alternate" type="application/rss+xml" title="{{ site.name | escape }} - {{ topic.name | escape }}" href="/{{ topic.slug }}/rss.xml">
+{%- elif tag %}
+<link rel="alternate" type="application/rss+xml" title="{{ site.name | escape }} - {{ tag.name | escape }}" href="{{ tag.url }}/rss.xml">
+{%- endif %}
 </head>
 <body>
 <header>
-<h1>{{ site.name }}</h1>
+<h1>{{ site.name | escape }}</h1>
 <nav>
 <a href="/">Home</a>
 {%- for topic in site.topics %}
-<a href="/{{ topic | slugify }}">{{ topic }}</a>
+<a href="/{{ topic | slugify }}">{{ topic | escape }}</a>
 {%- endfor %}
+<a href="/archive">Archive</a>
 <a href="/rss.xml">RSS</a>
+<form action="/search" method="get">
+<input type="search" name="q" placeholder="Search"{% if query %} value="{{ query | escape }}"{% endif %}>
+</form>
 </nav>
 </header>
 <main>
@@ -61,17 +70,86 @@ function change_img(dir) {
 <button type="button" onclick="change_img('next'); return false">❯</button>
 </center>
 {% elif post %}
+{%- if post_meta.draft %}
+<p><mark>DRAFT</mark></p>
+{%- endif %}
 {{ post }}
+{%- if post_meta.tags %}
+<p>Tags:
+{%- for tag in post_meta.tags %}
+<a href="{{ tag.url }}">{{ tag.name | escape }}</a>
+{%- endfor %}
+</p>
+{%- endif %}
+{% elif tags %}
+<h2>Tags</h2>
+<ul>
+{%- for tag in tags %}
+<li><a href="{{ tag.url }}">{{ tag.name | escape }}</a> ({{ tag.count }})</li>
+{%- endfor %}
+</ul>
+{% elif query is defined %}
+{%- if results %}
+<h2>Results for {{ query | escape }}</h2>
+{%- for result in results %}
+<article>
+<h3><a href="{{ result.url }}">{{ result.title | escape }}</a></h3>
+<p>{{ result.snippet }}</p>
+<p><small>{{ result.topic | escape }}</small></p>
+</article>
+{%- endfor %}
+{%- else %}
+<h2>No results for {{ query | escape }}</h2>
+{%- endif %}
+{% elif archive %}
+<h2>Archive</h2>
+{%- for year in archive.years %}
+<h3><a href="{{ year.url }}">{{ year.year }}</a></h3>
+{%- for month in year.months %}
+<h4><a href="{{ month.url }}">{{ month.name }}</a></h4>
+<ul>
+{%- for post in month.posts %}
+<li><a href="{{ post.url }}">{{ post.title | default(value=post.slug) | escape }}</a> <time datetime="{{ post.date }}">{{ post.date | date(format="%Y-%m-%d") }}</time></li>
+{%- endfor %}
+</ul>
+{%- endfor %}
+{%- endfor %}
 {% elif posts %}
-{%- for post in posts %}
-{{ post }}
-{%- endfor -%}
+{%- if tag %}
+<h2>Posts tagged {{ tag.name | escape }}</h2>
+{%- endif %}
+{%- for post in posts_meta %}
+{%- if post.draft %}
+<p><mark>DRAFT</mark></p>
+{%- endif %}
+{%- if site.excerpts %}
+<article>
+{{ post.excerpt }}
+{%- if post.has_more %}
+<p><a href="{{ post.url }}">Read more{% if post.title %} of {{ post.title | escape }}{% endif %} ❯</a></p>
+{%- endif %}
+</article>
+{%- else %}
+{{ post.html }}
+{%- endif %}
+{%- endfor %}
+{%- if pagination and pagination.total > 1 %}
+<nav>
+{%- if pagination.prev %}
+<a href="{{ pagination.prev }}">❮ Newer</a>
+{%- endif %}
+<span>Page {{ pagination.current }} of {{ pagination.total }}</span>
+{%- if pagination.next %}
+<a href="{{ pagination.next }}">Older ❯</a>
+{%- endif %}
+</nav>
+{%- endif %}
 {% else %}
 <h3>Coming Soon!</h3>
 {% endif %}
 </main>
 <footer>
-<p>&#169; {{ site.author }}</p>
+<p>&#169; {{ site.author | escape }}</p>
 </footer>
 </body>
 </html>
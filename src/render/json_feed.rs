@@ -0,0 +1,69 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides the document types of a [JSON Feed 1.1](https://jsonfeed.org/version/1.1).
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Value of the `version` field identifying JSON Feed 1.1.
+const VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// A JSON Feed document.
+#[derive(Debug, Serialize)]
+pub(crate) struct JsonFeed {
+    version: &'static str,
+    pub title: String,
+    pub home_page_url: String,
+    pub feed_url: String,
+    pub authors: Vec<Author>,
+    pub items: Vec<JsonItem>,
+}
+
+impl JsonFeed {
+    /// Creates a [`JsonFeed`] for a site served from `home_page_url`.
+    pub(crate) fn new(title: &str, home_page_url: &str, author: &str) -> JsonFeed {
+        let home_page_url = home_page_url.trim_end_matches('/');
+        JsonFeed {
+            version: VERSION,
+            title: title.to_owned(),
+            home_page_url: home_page_url.to_owned(),
+            feed_url: format!("{}/feed.json", home_page_url),
+            authors: vec![Author {
+                name: author.to_owned(),
+            }],
+            items: Vec::new(),
+        }
+    }
+}
+
+/// An author of a [`JsonFeed`] or one of its items.
+#[derive(Debug, Serialize)]
+pub(crate) struct Author {
+    pub name: String,
+}
+
+/// A single post within a [`JsonFeed`].
+#[derive(Debug, Serialize)]
+pub(crate) struct JsonItem {
+    pub id: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub content_html: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub date_published: DateTime<Utc>,
+    pub date_modified: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<Author>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
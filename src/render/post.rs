@@ -0,0 +1,502 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides post metadata parsed from optional TOML (`+++`) or YAML (`---`) front matter.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use log::{debug, trace};
+use pulldown_cmark::{html, Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::tags::TagLink;
+use super::{Context, Result};
+
+/// Metadata describing a post, as given in its front matter.
+///
+/// Every field is optional so that posts without front matter, or with only
+/// some fields set, render exactly as they did before metadata existed. Unknown
+/// fields are rejected, so that a `---` block of prose is not mistaken for YAML.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct PostMeta {
+    pub title: Option<String>,
+    #[serde(deserialize_with = "de_date")]
+    pub date: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "de_date")]
+    pub updated: Option<DateTime<Utc>>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    pub summary: Option<String>,
+    /// Keeps the post off the site unless drafts are shown with `arse run --drafts`.
+    pub draft: bool,
+    /// Keeps the post off the site until this time has passed.
+    #[serde(deserialize_with = "de_date")]
+    pub publish_date: Option<DateTime<Utc>>,
+}
+
+/// Marks the end of a post's excerpt when placed on its own in the Markdown body.
+const MORE_MARKER: &str = "<!-- more -->";
+
+/// A rendered post and its metadata, as exposed to the Tera context.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Post {
+    pub html: String,
+    pub meta: PostMeta,
+    /// Publish date resolved by [`publish_date`].
+    pub date: DateTime<Utc>,
+    /// Last update from [`PostMeta`], or the file's modification time, and never before `date`.
+    pub updated: DateTime<Utc>,
+    /// Slug of the topic the post belongs to, or `main`.
+    pub topic: String,
+    /// Filename of the post without its extension.
+    pub slug: String,
+    /// URL path serving the full post.
+    pub url: String,
+    /// Title from [`PostMeta`], or the text of the post's first heading.
+    pub title: Option<String>,
+    /// HTML rendered up to [`MORE_MARKER`], or through the first paragraphs of the post.
+    pub excerpt: String,
+    /// Whether the post continues beyond its excerpt.
+    pub has_more: bool,
+    /// Whether the post is a draft or scheduled for later, and only shown with `arse run --drafts`.
+    pub draft: bool,
+    /// Tags from [`PostMeta`], each linked to its page.
+    pub tags: Vec<TagLink>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Post {
+    /// Parses front matter, if any, from `buf` and renders the remaining Markdown to HTML.
+    ///
+    /// `path` and `modified` describe the file `buf` was read from, and are used to resolve the
+    /// post's publish date when its metadata does not provide one. Without a [`MORE_MARKER`], the
+    /// excerpt ends after `excerpt_paragraphs` top-level paragraphs.
+    pub(crate) fn parse<P: AsRef<Path>>(
+        path: P,
+        buf: &str,
+        modified: DateTime<Utc>,
+        excerpt_paragraphs: usize,
+    ) -> Result<Post> {
+        let path = path.as_ref();
+        let (meta, body) = split_front_matter(buf)?;
+        let parser = Parser::new(body);
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, parser);
+        let date = publish_date(&meta, path, modified);
+        let updated = meta.updated.unwrap_or(modified).max(date);
+        let (excerpt, has_more) = excerpt(body, excerpt_paragraphs);
+        let title = meta.title.clone().or_else(|| first_heading(body));
+
+        let slug = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_owned();
+        let topic = path
+            .parent()
+            .and_then(Path::parent)
+            .and_then(Path::file_name)
+            .and_then(|s| s.to_str())
+            .unwrap_or("main")
+            .to_owned();
+        let url = format!("/{}/posts/{}", topic, slug);
+
+        let tags = TagLink::from_names(&meta.tags);
+
+        Ok(Post {
+            html: html_output,
+            meta,
+            date,
+            updated,
+            topic,
+            slug,
+            url,
+            title,
+            excerpt,
+            has_more,
+            draft: false,
+            tags,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+/// Renders the excerpt of a Markdown body, returning it and whether any content was left out.
+fn excerpt(body: &str, paragraphs: usize) -> (String, bool) {
+    let events: Vec<Event> = Parser::new(body).collect();
+    let mut html_output = String::new();
+    if let Some((start, end)) = find_more_marker(&events) {
+        trace!("Cutting excerpt at '{}'", MORE_MARKER);
+        html::push_html(&mut html_output, events[..start].iter().cloned());
+        return (html_output, end + 1 < events.len());
+    }
+
+    let (mut depth, mut count, mut taken) = (0usize, 0usize, 0usize);
+    for event in &events {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(tag) => {
+                depth -= 1;
+                if depth == 0 && *tag == TagEnd::Paragraph {
+                    count += 1;
+                }
+            }
+            _ => {}
+        }
+        taken += 1;
+        if depth == 0 && count >= paragraphs.max(1) {
+            break;
+        }
+    }
+
+    html::push_html(&mut html_output, events[..taken].iter().cloned());
+    (html_output, taken < events.len())
+}
+
+/// Returns the first and last event of a top-level HTML block holding only [`MORE_MARKER`].
+///
+/// The marker is ignored anywhere else, such as within code blocks or inline in a paragraph.
+fn find_more_marker(events: &[Event]) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    for (idx, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::HtmlBlock) if depth == 0 => {
+                let end = idx
+                    + events[idx..]
+                        .iter()
+                        .position(|e| matches!(e, Event::End(TagEnd::HtmlBlock)))?;
+                let html: String = events[idx + 1..end]
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::Html(html) => Some(html.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                if html.trim() == MORE_MARKER {
+                    return Some((idx, end));
+                }
+                depth += 1;
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the plain text of the first heading in a Markdown body.
+fn first_heading(body: &str) -> Option<String> {
+    let mut title: Option<String> = None;
+    for event in Parser::new(body) {
+        match (event, title.as_mut()) {
+            (Event::Start(Tag::Heading { .. }), None) => title = Some(String::new()),
+            (Event::Text(text) | Event::Code(text), Some(t)) => t.push_str(&text),
+            (Event::End(TagEnd::Heading(_)), Some(_)) => break,
+            _ => {}
+        }
+    }
+
+    title.map(|t| t.trim().to_owned()).filter(|t| !t.is_empty())
+}
+
+/// Resolves a post's publish date.
+///
+/// In order of preference this is the `date` or `publish_date` from its [`PostMeta`], a
+/// `YYYY-MM-DD` prefix on its filename, or the file's modification time.
+fn publish_date<P: AsRef<Path>>(
+    meta: &PostMeta,
+    path: P,
+    modified: DateTime<Utc>,
+) -> DateTime<Utc> {
+    if let Some(date) = meta.date.or(meta.publish_date) {
+        return date;
+    }
+
+    let stem = path
+        .as_ref()
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    if let Some(Ok(d)) = stem
+        .get(..10)
+        .map(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d"))
+    {
+        trace!("Using filename date prefix for '{}'", stem);
+        return d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    }
+
+    modified
+}
+
+/// Splits a post into its [`PostMeta`] and Markdown body.
+///
+/// Front matter must open on the first line of the file with `+++` (TOML) or
+/// `---` (YAML), and close with the same delimiter on a line of its own. Files
+/// without a complete front matter block are returned untouched, as are those
+/// whose `---` block is empty or not valid YAML metadata, since `---` is also a
+/// Markdown thematic break.
+fn split_front_matter(buf: &str) -> Result<(PostMeta, &str)> {
+    let buf_start = buf.trim_start_matches('\u{feff}');
+    let delim = match buf_start.lines().next().map(str::trim_end) {
+        Some("+++") => "+++",
+        Some("---") => "---",
+        _ => return Ok((PostMeta::default(), buf)),
+    };
+
+    let rest = &buf_start[buf_start.find('\n').map_or(buf_start.len(), |i| i + 1)..];
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delim {
+            let front = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            trace!("Parsing post front matter delimited by '{}'", delim);
+            let meta = match delim {
+                "+++" => toml::from_str(front).context("failed to parse TOML front matter")?,
+                _ if front.trim().is_empty() => {
+                    debug!("Treating empty '---' block as Markdown");
+                    return Ok((PostMeta::default(), buf));
+                }
+                _ => match serde_yaml::from_str(front) {
+                    Ok(meta) => meta,
+                    Err(err) => {
+                        debug!(
+                            "Treating '---' block as Markdown, not YAML front matter: {}",
+                            err
+                        );
+                        return Ok((PostMeta::default(), buf));
+                    }
+                },
+            };
+            return Ok((meta, body));
+        }
+        offset += line.len();
+    }
+
+    trace!(
+        "No closing '{}' found, treating post as plain Markdown",
+        delim
+    );
+    Ok((PostMeta::default(), buf))
+}
+
+/// Dates may be given as native TOML datetimes or as strings in YAML and TOML.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDate {
+    Toml(toml::value::Datetime),
+    Text(String),
+}
+
+fn de_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+    let raw: Option<RawDate> = Option::deserialize(deserializer)?;
+    let text = match raw {
+        Some(RawDate::Toml(dt)) => dt.to_string(),
+        Some(RawDate::Text(s)) => s,
+        None => return Ok(None),
+    };
+
+    parse_date(&text)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses RFC 3339 timestamps, `YYYY-MM-DD HH:MM:SS`, or bare `YYYY-MM-DD` dates as UTC.
+fn parse_date(text: &str) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    for fmt in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, fmt) {
+            return Ok(dt.and_utc());
+        }
+    }
+
+    if let Ok(d) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    Err(anyhow!("unrecognized date format: '{}'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Result<Post> {
+        Post::parse("webroot/one/posts/post.md", src, DateTime::UNIX_EPOCH, 1)
+    }
+
+    #[test]
+    fn no_front_matter() {
+        let src = "\n### Something\n\nVery cool\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.meta, PostMeta::default());
+        assert_eq!(post.html, "<h3>Something</h3>\n<p>Very cool</p>\n");
+    }
+
+    #[test]
+    fn toml_front_matter() {
+        let src = r#"+++
+title = "Hello"
+date = 2024-05-01
+author = "Neo"
+tags = ["rust", "web"]
++++
+### Hello
+
+Body
+"#;
+        let post = parse(src).unwrap();
+        assert_eq!(post.meta.title.as_deref(), Some("Hello"));
+        assert_eq!(post.meta.author.as_deref(), Some("Neo"));
+        assert_eq!(post.meta.tags, vec!["rust", "web"]);
+        assert_eq!(
+            post.meta.date.unwrap().to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+        assert!(!post.html.contains("+++"));
+        assert!(post.html.contains("<p>Body</p>"));
+    }
+
+    #[test]
+    fn yaml_front_matter() {
+        let src = "---\ntitle: Hi\ndate: 2024-05-01T10:30:00Z\nsummary: Short\n---\nBody\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.meta.title.as_deref(), Some("Hi"));
+        assert_eq!(post.meta.summary.as_deref(), Some("Short"));
+        assert_eq!(
+            post.meta.date.unwrap().to_rfc3339(),
+            "2024-05-01T10:30:00+00:00"
+        );
+        assert_eq!(post.html, "<p>Body</p>\n");
+        assert_eq!(post.updated, post.date);
+
+        let src = "---\ndate: 2024-05-01\nupdated: 2024-06-01\n---\nBody\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.updated.to_rfc3339(), "2024-06-01T00:00:00+00:00");
+
+        let src = "---\ndraft: true\npublish_date: 2030-01-01 09:00\n---\nBody\n";
+        let post = parse(src).unwrap();
+        assert!(post.meta.draft);
+        assert_eq!(
+            post.meta.publish_date.unwrap().to_rfc3339(),
+            "2030-01-01T09:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn unclosed_front_matter() {
+        let src = "---\nNot actually front matter\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.meta, PostMeta::default());
+        assert!(post.html.contains("<hr />"));
+    }
+
+    #[test]
+    fn thematic_breaks_not_front_matter() {
+        let src = "---\n\nSome prose, with: a colon\n\n- and\n- a list\n\n---\n\nMore prose\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.meta, PostMeta::default());
+        assert_eq!(post.html.matches("<hr />").count(), 2);
+        assert!(post.html.contains("<p>More prose</p>"));
+
+        let src = "---\nNote: read this first\n---\nBody\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.meta, PostMeta::default());
+        assert!(post.html.contains("Note: read this first"));
+
+        let post = parse("---\n---\nBody\n").unwrap();
+        assert_eq!(post.html.matches("<hr />").count(), 2);
+        assert!(post.html.contains("<p>Body</p>"));
+    }
+
+    #[test]
+    fn post_excerpt() {
+        let src =
+            "# Heading `code`\n\nFirst paragraph\n\n> quoted\n>\n> twice\n\nSecond paragraph\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.title.as_deref(), Some("Heading code"));
+        assert_eq!(post.topic, "one");
+        assert_eq!(post.slug, "post");
+        assert_eq!(post.url, "/one/posts/post");
+        assert_eq!(
+            post.excerpt,
+            "<h1>Heading <code>code</code></h1>\n<p>First paragraph</p>\n"
+        );
+        assert!(post.has_more);
+
+        let (html, more) = excerpt(src, 3);
+        assert!(html.contains("Second paragraph"));
+        assert!(!more);
+
+        let src = "+++\ntitle = \"Meta\"\n+++\n# Heading\n\nIntro\n<!-- more -->\n\nRest\n";
+        let post = parse(src).unwrap();
+        assert_eq!(post.title.as_deref(), Some("Meta"));
+        assert_eq!(post.excerpt, "<h1>Heading</h1>\n<p>Intro</p>\n");
+        assert!(post.has_more);
+        assert!(post.html.contains("Rest"));
+
+        let src = "Intro\n\n```html\n<!-- more -->\nmarkup\n```\n\nRest\n";
+        let (html, more) = excerpt(src, 3);
+        assert!(html.contains("&lt;!-- more --&gt;\nmarkup"));
+        assert!(html.contains("Rest"));
+        assert!(!more);
+
+        let (html, more) = excerpt("Intro <!-- more --> inline\n\nRest\n", 1);
+        assert_eq!(html, "<p>Intro <!-- more --> inline</p>\n");
+        assert!(more);
+
+        let (html, more) = excerpt("Intro\n\n<!-- more -->\n", 5);
+        assert_eq!(html, "<p>Intro</p>\n");
+        assert!(!more);
+    }
+
+    #[test]
+    fn resolve_publish_date() {
+        let modified = DateTime::UNIX_EPOCH;
+        let mut meta = PostMeta::default();
+        assert_eq!(publish_date(&meta, "plain.md", modified), modified);
+        assert_eq!(publish_date(&meta, "2024.md", modified), modified);
+        assert_eq!(
+            publish_date(&meta, "posts/2024-05-01-foo.md", modified).to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+
+        meta.publish_date = Some(parse_date("2030-01-01").unwrap());
+        assert_eq!(
+            publish_date(&meta, "posts/2024-05-01-foo.md", modified).to_rfc3339(),
+            "2030-01-01T00:00:00+00:00"
+        );
+
+        meta.date = Some(parse_date("2023-01-02T03:04:05Z").unwrap());
+        assert_eq!(
+            publish_date(&meta, "posts/2024-05-01-foo.md", modified).to_rfc3339(),
+            "2023-01-02T03:04:05+00:00"
+        );
+    }
+
+    #[test]
+    fn invalid_front_matter() {
+        let src = "+++\ntitle = \n+++\nBody\n";
+        assert!(parse(src).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides full-text search over every post, served from `/search` and `/search.json`.
+//!
+//! Post text is stemmed with the Snowball English stemmer into an inverted index, and matches are
+//! ranked with [BM25](https://en.wikipedia.org/wiki/Okapi_BM25). The index lives in the
+//! [`RenderCache`](super::RenderCache) and is reloaded whenever the cache is cleared. It is also
+//! saved to [`SEARCH_INDEX_FILE`] in the webroot, and rebuilt from the posts only when they have
+//! changed since it was saved.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use log::{debug, trace, warn};
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use tera::Context as TemplateContext;
+
+use super::common;
+use super::{Context, Result};
+use super::{Engine, Post};
+
+/// Name of the file in the webroot that keeps the search index between runs.
+pub(crate) const SEARCH_INDEX_FILE: &str = ".search-index.json";
+
+/// Maximum number of results returned for a query.
+const MAX_RESULTS: usize = 20;
+
+/// Number of words shown in a result's snippet.
+const SNIPPET_WORDS: usize = 30;
+
+/// Times each word of a post's title is counted, so that title matches rank higher.
+const TITLE_WEIGHT: usize = 2;
+
+/// BM25 term frequency saturation.
+const K1: f64 = 1.2;
+
+/// BM25 document length normalization.
+const B: f64 = 0.75;
+
+/// Common English words left out of the index.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// A post matching a search, exposed to the Tera context as each of `results`.
+#[derive(Debug, Serialize)]
+pub(crate) struct SearchResult {
+    /// Title of the post, or its slug when untitled.
+    pub title: String,
+    pub url: String,
+    /// Name of the topic the post belongs to, or `main`.
+    pub topic: String,
+    /// HTML excerpt of the post's text around the first match, with matching words in `<mark>`.
+    ///
+    /// Each word is already escaped, so templates output it as is rather than through `escape`.
+    pub snippet: String,
+    pub score: f64,
+}
+
+/// Body of `/search.json`.
+#[derive(Debug, Serialize)]
+struct SearchResponse<'a> {
+    query: &'a str,
+    results: Vec<SearchResult>,
+}
+
+/// A post as recorded in a [`SearchIndex`].
+#[derive(Debug, Deserialize, Serialize)]
+struct Document {
+    title: String,
+    url: String,
+    topic: String,
+    /// Plain text of the post's rendered HTML.
+    text: String,
+    /// Number of indexed terms, including weighted title terms.
+    len: usize,
+}
+
+/// Inverted index from stemmed terms to the posts containing them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct SearchIndex {
+    docs: Vec<Document>,
+    /// Each term's postings as pairs of document index and term frequency.
+    postings: HashMap<String, Vec<(usize, usize)>>,
+    avg_len: f64,
+}
+
+impl SearchIndex {
+    /// Indexes `posts`, naming each post's topic with `topic_name`.
+    fn new(posts: Vec<Post>, topic_name: impl Fn(&str) -> String) -> SearchIndex {
+        let stemmer = Stemmer::create(Algorithm::English);
+        let mut index = SearchIndex::default();
+        for post in posts {
+            let text = html_to_text(&post.html);
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            let mut len = 0;
+            for term in terms(&stemmer, &text) {
+                *counts.entry(term).or_default() += 1;
+                len += 1;
+            }
+            for term in terms(&stemmer, post.title.as_deref().unwrap_or_default()) {
+                *counts.entry(term).or_default() += TITLE_WEIGHT;
+                len += TITLE_WEIGHT;
+            }
+
+            let doc = index.docs.len();
+            for (term, count) in counts {
+                index.postings.entry(term).or_default().push((doc, count));
+            }
+            index.docs.push(Document {
+                title: post.title.unwrap_or_else(|| post.slug.clone()),
+                topic: topic_name(&post.topic),
+                url: post.url,
+                text,
+                len,
+            });
+        }
+
+        let total: usize = index.docs.iter().map(|doc| doc.len).sum();
+        index.avg_len = total as f64 / index.docs.len().max(1) as f64;
+        debug!(
+            "Indexed {} posts with {} terms for search",
+            index.docs.len(),
+            index.postings.len()
+        );
+        index
+    }
+
+    /// Returns the best matches for any word of `query`, highest scoring first.
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        let stemmer = Stemmer::create(Algorithm::English);
+        let mut query_terms: Vec<String> = terms(&stemmer, query).collect();
+        query_terms.sort();
+        query_terms.dedup();
+
+        let total = self.docs.len() as f64;
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for term in &query_terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let found = postings.len() as f64;
+            let idf = (1.0 + (total - found + 0.5) / (found + 0.5)).ln();
+            for &(doc, count) in postings {
+                let tf = count as f64;
+                let norm = 1.0 - B + B * self.docs[doc].len as f64 / self.avg_len;
+                *scores.entry(doc).or_default() += idf * tf * (K1 + 1.0) / (tf + K1 * norm);
+            }
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(MAX_RESULTS);
+        trace!("Search for '{}' matched {} posts", query, ranked.len());
+
+        ranked
+            .into_iter()
+            .map(|(doc, score)| {
+                let doc = &self.docs[doc];
+                SearchResult {
+                    title: doc.title.clone(),
+                    url: doc.url.clone(),
+                    topic: doc.topic.clone(),
+                    snippet: snippet(&stemmer, &doc.text, &query_terms),
+                    score,
+                }
+            })
+            .collect()
+    }
+}
+
+/// What a [`SearchIndex`] was built from, so that a saved index is only reused while it still applies.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Sources {
+    drafts: bool,
+    /// Slug and name of each topic indexed, `main` first.
+    topics: Vec<(String, String)>,
+    /// Path, length, and modification time of each post file.
+    files: Vec<(PathBuf, u64, SystemTime)>,
+}
+
+/// Contents of [`SEARCH_INDEX_FILE`].
+#[derive(Debug, Deserialize, Serialize)]
+struct SavedIndex<I> {
+    sources: Sources,
+    /// When the earliest scheduled post was due as the index was built, after which it is stale.
+    due: Option<DateTime<Utc>>,
+    index: I,
+}
+
+impl SavedIndex<SearchIndex> {
+    /// Reads the index saved at `path`, if it was built from `sources` and is not yet due.
+    fn load(path: &Path, sources: &Sources) -> Option<SearchIndex> {
+        let json = std::fs::read(path).ok()?;
+        let saved: SavedIndex<SearchIndex> = match serde_json::from_slice(&json) {
+            Ok(saved) => saved,
+            Err(err) => {
+                warn!(
+                    "Ignoring unreadable search index '{}': {}",
+                    path.display(),
+                    err
+                );
+                return None;
+            }
+        };
+        if &saved.sources != sources {
+            debug!("Saved search index is out of date with the posts");
+            return None;
+        }
+        if saved.due.is_some_and(|due| due <= Utc::now()) {
+            debug!("Saved search index predates a scheduled post");
+            return None;
+        }
+        Some(saved.index)
+    }
+}
+
+impl SavedIndex<&SearchIndex> {
+    /// Replaces the file at `path` with this index.
+    fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).context("failure serializing search index")?;
+        common::replace_ro_file(&json, path)
+    }
+}
+
+/// Splits `text` into lowercase, stemmed terms, leaving out [`STOP_WORDS`].
+fn terms<'a>(stemmer: &'a Stemmer, text: &'a str) -> impl Iterator<Item = String> + 'a {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| stemmer.stem(&word).into_owned())
+}
+
+/// Returns up to [`SNIPPET_WORDS`] words of `text` around the first word matching `query_terms`.
+fn snippet(stemmer: &Stemmer, text: &str, query_terms: &[String]) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let matches = |word: &str| terms(stemmer, word).any(|term| query_terms.contains(&term));
+    let first = words.iter().position(|word| matches(word)).unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_WORDS / 3);
+    let end = (start + SNIPPET_WORDS).min(words.len());
+
+    let mut output = String::new();
+    if start > 0 {
+        output.push_str("… ");
+    }
+    for (idx, word) in words[start..end].iter().enumerate() {
+        if idx > 0 {
+            output.push(' ');
+        }
+        if matches(word) {
+            output.push_str("<mark>");
+            output.push_str(&escape(word));
+            output.push_str("</mark>");
+        } else {
+            output.push_str(&escape(word));
+        }
+    }
+    if end < words.len() {
+        output.push_str(" …");
+    }
+    output
+}
+
+/// Strips tags from rendered HTML and decodes the entities produced by Markdown rendering.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Engine {
+    /// Returns the [`SearchIndex`] of posts from `main` and every topic, building it if needed.
+    pub(crate) async fn search_index(&self) -> Result<Arc<SearchIndex>> {
+        if let Some(index) = self.cache.search_index() {
+            return Ok(index);
+        }
+
+        let generation = self.cache.generation();
+        let path = Path::new(&self.app.docpaths.webroot).join(SEARCH_INDEX_FILE);
+        // Read before the posts, so that any change made while building makes the saved index stale.
+        let sources = self.search_sources()?;
+        if let Some(index) = SavedIndex::load(&path, &sources) {
+            debug!("Loaded search index from '{}'", path.display());
+            let index = Arc::new(index);
+            self.cache.set_search_index(index.clone(), generation);
+            return Ok(index);
+        }
+
+        debug!("Building search index");
+        let posts = self.all_posts().await?;
+        let index = Arc::new(SearchIndex::new(posts, |slug| {
+            self.topic_name(slug).unwrap_or(slug).to_owned()
+        }));
+        let saved = SavedIndex {
+            sources,
+            due: self.cache.expiry(),
+            index: index.as_ref(),
+        };
+        if let Err(err) = saved.save(&path) {
+            warn!("Failed to save search index: {:#}", err);
+        }
+        self.cache.set_search_index(index.clone(), generation);
+        Ok(index)
+    }
+
+    /// Lists what the search index is built from, for comparison with a saved index.
+    fn search_sources(&self) -> Result<Sources> {
+        let webroot = Path::new(&self.app.docpaths.webroot);
+        let mut topics = Vec::new();
+        let mut files = Vec::new();
+        for slug in std::iter::once("main").chain(self.topic_slugs.iter().map(String::as_str)) {
+            topics.push((
+                slug.to_owned(),
+                self.topic_name(slug).unwrap_or(slug).to_owned(),
+            ));
+            let pat = format!("{}/*.md", webroot.join(slug).join("posts").display());
+            for path in common::path_matches(&pat)? {
+                let metadata = std::fs::metadata(&path)
+                    .with_context(|| format!("failed to read metadata of '{}'", path.display()))?;
+                let modified = metadata.modified().with_context(|| {
+                    format!("failed to read modification time of '{}'", path.display())
+                })?;
+                files.push((path, metadata.len(), modified));
+            }
+        }
+
+        Ok(Sources {
+            drafts: self.drafts,
+            topics,
+            files,
+        })
+    }
+
+    /// Returns the posts best matching `query`.
+    pub(crate) async fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        Ok(self.search_index().await?.search(query))
+    }
+
+    /// Renders `/search?q=...`, listing the posts matching `query`
+    pub(crate) async fn render_search(&self, query: &str) -> Result<String> {
+        debug!("Rendering search for: '{}'", query);
+        let site = &self.app.site;
+        let mut context = TemplateContext::new();
+        context.insert("site", site);
+        context.insert("query", query);
+        context.insert("results", &self.search(query).await?);
+
+        self.instance
+            .render(&site.template, &context)
+            .with_context(|| {
+                format!(
+                    "failed rendering search, with Tera instance: {:?}",
+                    self.instance
+                )
+            })
+    }
+
+    /// Renders `/search.json?q=...`, listing the posts matching `query`
+    pub(crate) async fn search_json(&self, query: &str) -> Result<String> {
+        let response = SearchResponse {
+            query,
+            results: self.search(query).await?,
+        };
+        serde_json::to_string_pretty(&response).context("failure serializing search results")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::testing;
+
+    #[test]
+    fn text_terms_and_snippets() {
+        let text = html_to_text("<h1>Fish &amp; Chips</h1>\n<p>Catching <em>fishes</em></p>");
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            ["Fish", "&", "Chips", "Catching", "fishes"]
+        );
+
+        let stemmer = Stemmer::create(Algorithm::English);
+        let found: Vec<_> = terms(&stemmer, "The Running runners, and RUNS").collect();
+        assert_eq!(found, ["run", "runner", "run"]);
+
+        let query = vec!["fish".to_owned()];
+        assert_eq!(
+            snippet(&stemmer, &text, &query),
+            "<mark>Fish</mark> &amp; Chips Catching <mark>fishes</mark>"
+        );
+        let long = format!("{} fishing <b>", "word ".repeat(40));
+        let output = snippet(&stemmer, &long, &query);
+        assert!(output.starts_with("… word"));
+        assert!(output.contains("<mark>fishing</mark> &lt;b&gt;"));
+        assert!(!output.ends_with('…'));
+    }
+
+    #[tokio::test]
+    async fn check_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Two"]);
+        let engine = Engine::new(config).unwrap();
+
+        let files = [
+            (
+                "one/posts/a.md",
+                "# Gardening\n\nPlanting tomatoes and a tomato cage.\n",
+            ),
+            (
+                "two/posts/b.md",
+                "# Tomato Soup\n\nA recipe for soup with tomatoes.\n",
+            ),
+            ("main/posts/c.md", "Nothing to see here.\n"),
+        ];
+        for (name, content) in files {
+            let path = dir.path().join("site/webroot").join(name);
+            std::fs::write(path, content).unwrap();
+        }
+
+        let results = engine.search("Tomatoes").await.unwrap();
+        let found: Vec<_> = results
+            .iter()
+            .map(|r| (r.title.as_str(), r.url.as_str(), r.topic.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Tomato Soup", "/two/posts/b", "Two"),
+                ("Gardening", "/one/posts/a", "One"),
+            ]
+        );
+        assert!(results[1].snippet.contains("<mark>tomatoes</mark>"));
+        assert!(engine.search("the").await.unwrap().is_empty());
+        assert!(engine.search("").await.unwrap().is_empty());
+
+        let page = engine.render_search("soup").await.unwrap();
+        assert!(page.contains(r#"<a href="/two/posts/b">Tomato Soup</a>"#));
+        assert!(!page.contains("Gardening"));
+        let page = engine.render_search("<script>").await.unwrap();
+        assert!(page.contains("No results for &lt;script&gt;"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&engine.search_json("planting").await.unwrap()).unwrap();
+        assert_eq!(json["query"], "planting");
+        assert_eq!(json["results"][0]["url"], "/one/posts/a");
+
+        std::fs::write(
+            dir.path().join("site/webroot/main/posts/d.md"),
+            "# Planting Season\n",
+        )
+        .unwrap();
+        assert_eq!(engine.search("planting").await.unwrap().len(), 1);
+        engine.cache.clear();
+        assert_eq!(engine.search("planting").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn check_saved_search_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let posts = dir.path().join("site/webroot/one/posts");
+        std::fs::write(posts.join("a.md"), "# Tomato Soup\n\nSoup with tomatoes.\n").unwrap();
+        let saved = dir.path().join("site/webroot").join(SEARCH_INDEX_FILE);
+
+        let engine = Engine::new(config).unwrap();
+        assert_eq!(engine.search("soup").await.unwrap()[0].title, "Tomato Soup");
+        let json = std::fs::read_to_string(&saved).unwrap();
+        assert!(json.contains("Tomato Soup"));
+
+        // A fresh engine reuses the saved index while the posts are unchanged.
+        std::fs::write(&saved, json.replace("Tomato Soup", "Saved Soup")).unwrap();
+        let config = AppConfig::from_path(dir.path().join("config.toml")).unwrap();
+        let engine = Engine::new(config).unwrap();
+        assert_eq!(engine.search("soup").await.unwrap()[0].title, "Saved Soup");
+
+        // It is rebuilt once they change, or when the saved file cannot be read.
+        std::fs::write(posts.join("b.md"), "# Pea Soup\n").unwrap();
+        engine.cache.clear();
+        let mut titles: Vec<_> = engine
+            .search("soup")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|result| result.title)
+            .collect();
+        titles.sort();
+        assert_eq!(titles, ["Pea Soup", "Tomato Soup"]);
+        std::fs::write(&saved, "not json").unwrap();
+        engine.cache.clear();
+        assert_eq!(engine.search("soup").await.unwrap().len(), 2);
+        assert!(std::fs::read_to_string(&saved)
+            .unwrap()
+            .contains("Pea Soup"));
+    }
+}
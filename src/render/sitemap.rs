@@ -0,0 +1,120 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides XML sitemaps and sitemap indexes following the [sitemaps.org](https://www.sitemaps.org/protocol.html) protocol.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Maximum number of URLs the protocol allows in a single sitemap.
+pub(crate) const MAX_URLS: usize = 50_000;
+
+/// A single page listed in a sitemap.
+#[derive(Debug, PartialEq)]
+pub(crate) struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<DateTime<Utc>>,
+}
+
+/// Renders a `<urlset>` listing each of `urls`.
+pub(crate) fn urlset(urls: &[SitemapUrl]) -> String {
+    let mut output = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for url in urls {
+        output.push_str("<url>");
+        entry(&mut output, &url.loc, url.lastmod);
+        output.push_str("</url>\n");
+    }
+    output.push_str("</urlset>\n");
+    output
+}
+
+/// Renders a `<sitemapindex>` referencing `{base}/sitemap/{n}.xml` for each of `parts`, numbered from `1`.
+pub(crate) fn index<'a>(base: &str, parts: impl Iterator<Item = &'a [SitemapUrl]>) -> String {
+    let mut output = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (idx, part) in parts.enumerate() {
+        let loc = format!("{}/sitemap/{}.xml", base.trim_end_matches('/'), idx + 1);
+        let lastmod = part.iter().filter_map(|url| url.lastmod).max();
+        output.push_str("<sitemap>");
+        entry(&mut output, &loc, lastmod);
+        output.push_str("</sitemap>\n");
+    }
+    output.push_str("</sitemapindex>\n");
+    output
+}
+
+fn entry(output: &mut String, loc: &str, lastmod: Option<DateTime<Utc>>) {
+    let _ = write!(output, "<loc>{}</loc>", escape(loc));
+    if let Some(lastmod) = lastmod {
+        let _ = write!(
+            output,
+            "<lastmod>{}</lastmod>",
+            lastmod.to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sitemap_urlset_and_index() {
+        let urls = vec![
+            SitemapUrl {
+                loc: "https://example.site/".to_owned(),
+                lastmod: None,
+            },
+            SitemapUrl {
+                loc: "https://example.site/one/posts/a&b".to_owned(),
+                lastmod: Some(DateTime::UNIX_EPOCH),
+            },
+            SitemapUrl {
+                loc: "https://example.site/two".to_owned(),
+                lastmod: Some(DateTime::UNIX_EPOCH + chrono::Duration::days(1)),
+            },
+        ];
+
+        let output = urlset(&urls);
+        assert!(output.contains("<url><loc>https://example.site/</loc></url>"));
+        assert!(output.contains(
+            "<url><loc>https://example.site/one/posts/a&amp;b</loc><lastmod>1970-01-01T00:00:00Z</lastmod></url>"
+        ));
+
+        let output = index("https://example.site/", urls.chunks(2));
+        assert!(output.starts_with("<?xml"));
+        assert!(output.contains(
+            "<sitemap><loc>https://example.site/sitemap/1.xml</loc><lastmod>1970-01-01T00:00:00Z</lastmod></sitemap>"
+        ));
+        assert!(output.contains(
+            "<sitemap><loc>https://example.site/sitemap/2.xml</loc><lastmod>1970-01-02T00:00:00Z</lastmod></sitemap>"
+        ));
+        assert!(!output.contains("sitemap/3.xml"));
+    }
+}
@@ -0,0 +1,289 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides the tag taxonomy served from `/tags`, built from the `tags` of every post's metadata.
+//!
+//! Tags are grouped by slug, so `Rust` and `rust` are the same tag, named as first seen on the newest post.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use log::{debug, trace};
+use rss::Channel;
+use serde::Serialize;
+use tera::Context as TemplateContext;
+
+use super::{common, Engine, Post};
+use super::{Context, Result};
+
+/// A tag as listed on a single post, exposed to the Tera context as each of `post_meta.tags`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct TagLink {
+    pub name: String,
+    pub slug: String,
+    /// URL path of the tag's page.
+    pub url: String,
+}
+
+impl TagLink {
+    /// Links each of `names` to its tag's page, leaving out names with an empty or repeated slug.
+    pub(crate) fn from_names(names: &[String]) -> Vec<TagLink> {
+        let mut links: Vec<TagLink> = Vec::new();
+        for name in names {
+            let slug = common::slugify(name);
+            if slug.is_empty() || links.iter().any(|link| link.slug == slug) {
+                continue;
+            }
+            links.push(TagLink {
+                name: name.clone(),
+                url: tag_url(&slug),
+                slug,
+            });
+        }
+        links
+    }
+}
+
+/// Returns the URL path of the page for the tag with `slug`.
+fn tag_url(slug: &str) -> String {
+    format!("/tags/{}", common::encode_path_segment(slug))
+}
+
+/// Collects the tags of `posts`, ordered by slug.
+///
+/// `posts` are expected newest first, so that each tag is named as on the newest post using it.
+fn collect_tags(posts: &[Post]) -> Vec<Tag> {
+    let mut tags: BTreeMap<String, Tag> = BTreeMap::new();
+    for post in posts {
+        for link in &post.tags {
+            let tag = tags.entry(link.slug.clone()).or_insert_with(|| Tag {
+                name: link.name.clone(),
+                slug: link.slug.clone(),
+                url: link.url.clone(),
+                count: 0,
+                updated: post.updated,
+            });
+            tag.count += 1;
+            tag.updated = tag.updated.max(post.updated);
+        }
+    }
+    tags.into_values().collect()
+}
+
+/// A tag used by one or more posts, exposed to the Tera context as `tag`, or as each of `tags`.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Tag {
+    pub name: String,
+    pub slug: String,
+    /// URL path of the tag's page.
+    pub url: String,
+    /// Number of posts with the tag.
+    pub count: usize,
+    /// Latest `updated` of the posts with the tag.
+    pub updated: DateTime<Utc>,
+}
+
+impl Engine {
+    /// Returns every tag used across `main` and all topics, ordered by slug.
+    pub(crate) async fn tags(&self) -> Result<Vec<Tag>> {
+        Ok(collect_tags(&self.all_posts().await?))
+    }
+
+    /// Returns the tag with `tag_slug` and its posts, newest first, if any post uses it.
+    async fn tagged_posts(&self, tag_slug: &str) -> Result<Option<(Tag, Vec<Post>)>> {
+        let posts: Vec<Post> = self
+            .all_posts()
+            .await?
+            .into_iter()
+            .filter(|post| post.tags.iter().any(|link| link.slug == tag_slug))
+            .collect();
+        let tag = collect_tags(&posts)
+            .into_iter()
+            .find(|tag| tag.slug == tag_slug);
+        Ok(tag.map(|tag| (tag, posts)))
+    }
+
+    /// Renders `/tags`, listing every tag with its post count
+    pub(crate) async fn render_tags(&self) -> Result<String> {
+        if let Some(output) = self.cache.get("/tags") {
+            return Ok(output);
+        }
+        let generation = self.cache.generation();
+
+        debug!("Rendering tag index");
+        let site = &self.app.site;
+        let mut context = TemplateContext::new();
+        context.insert("site", site);
+        context.insert("tags", &self.tags().await?);
+
+        let output = self
+            .instance
+            .render(&site.template, &context)
+            .with_context(|| {
+                format!(
+                    "failed rendering tag index, with Tera instance: {:?}",
+                    self.instance
+                )
+            })?;
+
+        trace!("Rendered tag index\n{}", output);
+        self.cache
+            .insert("/tags".to_owned(), output.clone(), generation);
+        Ok(output)
+    }
+
+    /// Renders `/tags/:tag`, listing the posts with the tag across all topics
+    ///
+    /// Returns `None` when no post has the tag.
+    pub(crate) async fn render_tag(&self, tag_slug: &str) -> Result<Option<String>> {
+        let route = format!("/tags/{}", tag_slug);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(Some(output));
+        }
+        let generation = self.cache.generation();
+        let Some((tag, posts)) = self.tagged_posts(tag_slug).await? else {
+            return Ok(None);
+        };
+
+        debug!("Rendering tag: '{}'", tag_slug);
+        let site = &self.app.site;
+        let mut context = TemplateContext::new();
+        context.insert("site", site);
+        context.insert("tag", &tag);
+        super::insert_posts(&mut context, &posts);
+
+        let output = self
+            .instance
+            .render(&site.template, &context)
+            .with_context(|| {
+                format!(
+                    "failed rendering tag: {}, with Tera instance: {:?}",
+                    tag_slug, self.instance
+                )
+            })?;
+
+        trace!("Rendered content for tag: {}\n{}", tag_slug, output);
+        self.cache.insert(route, output.clone(), generation);
+        Ok(Some(output))
+    }
+
+    /// Renders `/tags/:tag/rss.xml` for the posts with a single tag
+    ///
+    /// Returns `None` when no post has the tag.
+    pub(crate) async fn tag_rss(&self, tag_slug: &str) -> Result<Option<String>> {
+        let route = format!("/tags/{}/rss.xml", tag_slug);
+        if let Some(output) = self.cache.get(&route) {
+            return Ok(Some(output));
+        }
+        let generation = self.cache.generation();
+        let Some((tag, posts)) = self.tagged_posts(tag_slug).await? else {
+            return Ok(None);
+        };
+
+        debug!("Rendering RSS Feed for tag: '{}'", tag_slug);
+        let site = &self.app.site;
+        let title = format!("{} - {}", &site.name, &tag.name);
+        let mut channel = Channel::default();
+        channel.set_link(format!("{}{}", site.url.trim_end_matches('/'), &tag.url));
+        channel.set_description(format!("{} RSS Feed", &title));
+        channel.set_title(title);
+        channel.set_items(
+            self.limit_feed(posts)
+                .into_iter()
+                .map(|post| self.post_to_item(post))
+                .collect::<Vec<_>>(),
+        );
+
+        let output = channel.to_string();
+        self.cache.insert(route, output.clone(), generation);
+        Ok(Some(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn check_render_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Two"]);
+        let engine = Engine::new(config).unwrap();
+
+        let files = [
+            (
+                "one/posts/2024-01-01-a.md",
+                "+++\ntags = [\"rust\", \"Web Dev\"]\n+++\n# Rust on One\n",
+            ),
+            (
+                "two/posts/2024-02-01-b.md",
+                "---\ntags: [Rust]\n---\n# Rust on Two\n",
+            ),
+            (
+                "main/posts/2023-12-01-c.md",
+                "+++\ntags = [\"C++\", \"c++\"]\n+++\n# Templates\n",
+            ),
+            ("main/posts/index.md", "# Untagged\n"),
+        ];
+        for (name, content) in files {
+            let path = dir.path().join("site/webroot").join(name);
+            std::fs::write(path, content).unwrap();
+        }
+
+        let tags = engine.tags().await.unwrap();
+        let summary: Vec<_> = tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.slug.as_str(), tag.count))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("C++", "c++", 1),
+                ("Rust", "rust", 2),
+                ("Web Dev", "web-dev", 1)
+            ]
+        );
+        assert_eq!(tags[0].url, "/tags/c%2B%2B");
+        assert_eq!(tags[1].url, "/tags/rust");
+
+        let index = engine.render_tags().await.unwrap();
+        assert!(index.contains(r#"<a href="/tags/web-dev">Web Dev</a> (1)"#));
+
+        let page = engine.render_tag("rust").await.unwrap().unwrap();
+        assert!(page.contains("Posts tagged Rust"));
+        let newer = page.find("Rust on Two").unwrap();
+        let older = page.find("Rust on One").unwrap();
+        assert!(newer < older);
+        assert!(!page.contains("Untagged"));
+        assert!(page.contains(r#"href="/tags/rust/rss.xml""#));
+        assert!(engine.render_tag("nope").await.unwrap().is_none());
+
+        let channel: Channel = engine
+            .tag_rss("web-dev")
+            .await
+            .unwrap()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(channel.title(), "Site Name - Web Dev");
+        assert_eq!(channel.items().len(), 1);
+        assert!(engine.tag_rss("nope").await.unwrap().is_none());
+
+        let post = engine.render_post("one", "2024-01-01-a").await.unwrap();
+        assert!(post.contains(r#"<a href="/tags/web-dev">Web Dev</a>"#));
+        let post = engine.render_post("main", "2023-12-01-c").await.unwrap();
+        assert_eq!(
+            post.matches(r#"<a href="/tags/c%2B%2B">C++</a>"#).count(),
+            1
+        );
+        assert!(engine.render_tag("c++").await.unwrap().is_some());
+    }
+}
@@ -15,38 +15,108 @@ use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::fs::File;
-use tokio::io::AsyncReadExt;
 
 use axum::{
-    body::Body,
-    extract::{Path as axumPath, State},
-    http::StatusCode,
+    body::{Body, HttpBody},
+    extract::{FromRef, Path as axumPath, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
     response::Response,
     routing::get,
     Router,
 };
 use log::{debug, error, info};
+use serde::Deserialize;
+use tower_http::compression::predicate::{And, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
 
 use crate::common::slugify;
 
-use super::render::Engine;
+use super::config::{Compression, RouteClass};
+use super::render::{Archive, Engine, SharedEngine};
 use super::{Context, Error, Result};
 
-/// Creates a [`Filter`] instance with a given [`Arc<Engine>`].
-pub(crate) fn router(engine: Arc<Engine>) -> Router {
+/// Streaming file responses for asset routes.
+mod assets;
+
+/// Creates a [`Router`] instance with a given [`SharedEngine`].
+///
+/// Handlers receive the current [`Engine`] at the time of each request.
+pub(crate) fn router(engine: SharedEngine) -> Router {
     debug!("Building site router");
+    let compression = compression_layer(&engine.load().app.compression);
     let router = Router::new()
         .route("/", get(index_handler))
+        .route("/page/{page}", get(index_page_handler))
         .route("/favicon.ico", get(favicon))
+        .route("/robots.txt", get(robots))
+        .route("/.well-known/{*fname}", get(well_known))
         .route("/rss.xml", get(rss_handler))
+        .route("/atom.xml", get(atom_handler))
+        .route("/feed.json", get(json_feed_handler))
+        .route("/sitemap.xml", get(sitemap_handler))
+        .route("/sitemap/{part}", get(sitemap_part_handler))
+        .route("/archive", get(archive_handler))
+        .route("/archive/{year}", get(archive_year_handler))
+        .route("/archive/{year}/{month}", get(archive_month_handler))
+        .route("/tags", get(tags_handler))
+        .route("/tags/{tag}", get(tag_handler))
+        .route("/tags/{tag}/rss.xml", get(tag_rss_handler))
+        .route("/search", get(search_handler))
+        .route("/search.json", get(search_json_handler))
         .route("/static/{*fname}", get(static_assets))
         .route("/{topic}/ext/{*fname}", get(topic_assets))
         .route("/{topic}/posts/{post}", get(post_handler))
+        .route("/{topic}/rss.xml", get(topic_rss_handler))
+        .route("/{topic}/page/{page}", get(topic_page_handler))
         .route("/{topic}", get(topic_handler))
         .with_state(engine);
 
-    router
+    match compression {
+        Some(layer) => router.layer(layer),
+        None => router,
+    }
+}
+
+/// Limits compression to the MIME types allowed by [`Compression`].
+///
+/// Files served from disk are left as they are: their strong `ETag` and byte ranges describe the
+/// file's own bytes, and any compressed form comes from a precompressed sibling instead.
+#[derive(Clone, Debug)]
+struct CompressibleTypes(Arc<Compression>);
+
+impl Predicate for CompressibleTypes {
+    fn should_compress<B: HttpBody>(&self, response: &Response<B>) -> bool {
+        if response.headers().contains_key(header::ETAG) {
+            return false;
+        }
+        response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| self.0.allows(content_type))
+    }
+}
+
+/// Builds the gzip and brotli [`CompressionLayer`] described by `[compression]`, if enabled.
+fn compression_layer(
+    compression: &Compression,
+) -> Option<CompressionLayer<And<SizeAbove, CompressibleTypes>>> {
+    if !compression.enabled {
+        debug!("Response compression disabled");
+        return None;
+    }
+
+    let types = CompressibleTypes(Arc::new(compression.clone()));
+    Some(
+        CompressionLayer::new()
+            .compress_when(SizeAbove::new(compression.size_threshold()).and(types)),
+    )
+}
+
+impl FromRef<SharedEngine> for Arc<Engine> {
+    fn from_ref(shared: &SharedEngine) -> Arc<Engine> {
+        shared.load()
+    }
 }
 
 /// Returns the MIME type given by the user's config for a particular extension.
@@ -64,32 +134,334 @@ fn mime_from_ext(ext: Option<&OsStr>, mime_map: &HashMap<String, String>) -> Str
     String::from("text/plain")
 }
 
+/// Optional `?page=N` query for topic pages
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    page: Option<String>,
+}
+
+impl PageQuery {
+    /// Requested page, defaulting to `1`.
+    fn page(&self) -> &str {
+        self.page.as_deref().unwrap_or("1")
+    }
+}
+
 /// Handler for "/"
-async fn index_handler(State(engine): State<Arc<Engine>>) -> Response<Body> {
+async fn index_handler(
+    Query(query): Query<PageQuery>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
     info!("Handling request to '/'");
-    topic_posts(engine.clone(), "main".to_owned())
+    let response = index_page(engine.clone(), query.page()).await;
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/page/:page"
+async fn index_page_handler(
+    axumPath(page): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/page/{}'", &page);
+    let response = index_page(engine.clone(), &page).await;
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Renders a page of the index, or a 404 when `page` is not a page number
+async fn index_page(engine: Arc<Engine>, page: &str) -> Response<Body> {
+    let Ok(page) = page.parse() else {
+        return server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Page {} of topic: main was not found", page),
+        );
+    };
+
+    topic_posts(engine, "main".to_owned(), page)
         .await
         .unwrap_or_else(|err| server_error(StatusCode::INTERNAL_SERVER_ERROR, err))
 }
 
 /// Handler for "/rss"
-async fn rss_handler(State(engine): State<Arc<Engine>>) -> Response<Body> {
+async fn rss_handler(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
     info!("Handling request to '/rss.xml'");
-    match engine.rss().await {
+    let response = match engine.rss().await {
         Ok(rss) => Response::builder()
             .header("content-type", "application/rss+xml")
             .body(Body::from(rss))
             .unwrap(),
         Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
+/// Handler for "/atom.xml"
+async fn atom_handler(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
+    info!("Handling request to '/atom.xml'");
+    let response = match engine.atom().await {
+        Ok(atom) => Response::builder()
+            .header("content-type", "application/atom+xml")
+            .body(Body::from(atom))
+            .unwrap(),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
+/// Handler for "/feed.json"
+async fn json_feed_handler(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
+    info!("Handling request to '/feed.json'");
+    let response = match engine.json_feed().await {
+        Ok(feed) => Response::builder()
+            .header("content-type", "application/feed+json")
+            .body(Body::from(feed))
+            .unwrap(),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
+/// Handler for "/sitemap.xml"
+async fn sitemap_handler(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
+    info!("Handling request to '/sitemap.xml'");
+    let response = match engine.sitemap().await {
+        Ok(sitemap) => Response::builder()
+            .header("content-type", "application/xml")
+            .body(Body::from(sitemap))
+            .unwrap(),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
+/// Handler for "/sitemap/:part.xml"
+async fn sitemap_part_handler(
+    axumPath(part): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/sitemap/{}'", &part);
+    let Some(part) = part
+        .strip_suffix(".xml")
+        .and_then(|n| n.parse::<usize>().ok())
+    else {
+        return server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Sitemap part: {} was not found", part),
+        );
+    };
+
+    let response = match engine.sitemap_part(part).await {
+        Ok(Some(sitemap)) => Response::builder()
+            .header("content-type", "application/xml")
+            .body(Body::from(sitemap))
+            .unwrap(),
+        Ok(None) => server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Sitemap part: {} was not found", part),
+        ),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
+/// Handler for "/archive"
+async fn archive_handler(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
+    info!("Handling request to '/archive'");
+    let response = archive_page(engine.clone(), None, None).await;
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/archive/:year"
+async fn archive_year_handler(
+    axumPath(year): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/archive/{}'", &year);
+    let response = match year.parse() {
+        Ok(year) => archive_page(engine.clone(), Some(year), None).await,
+        Err(_) => server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Archive: {} was not found", year),
+        ),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/archive/:year/:month"
+async fn archive_month_handler(
+    axumPath((year, month)): axumPath<(String, String)>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/archive/{}/{}'", &year, &month);
+    let response = match (year.parse(), month.parse()) {
+        (Ok(year), Ok(month)) => archive_page(engine.clone(), Some(year), Some(month)).await,
+        _ => server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Archive: {}/{} was not found", year, month),
+        ),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Renders an archive page, or a 404 when it has no posts
+async fn archive_page(
+    engine: Arc<Engine>,
+    year: Option<i32>,
+    month: Option<u32>,
+) -> Response<Body> {
+    match engine.render_archive(year, month).await {
+        Ok(Some(output)) => Response::builder()
+            .header("content-type", "text/html")
+            .body(Body::from(output))
+            .unwrap(),
+        Ok(None) => server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("No posts were found in '{}'", Archive::url(year, month)),
+        ),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
     }
 }
 
+/// Handler for "/tags"
+async fn tags_handler(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
+    info!("Handling request to '/tags'");
+    let response = match engine.render_tags().await {
+        Ok(output) => Response::builder()
+            .header("content-type", "text/html")
+            .body(Body::from(output))
+            .unwrap(),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// `?q=...` query for searches
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+/// Handler for "/search"
+async fn search_handler(
+    Query(query): Query<SearchQuery>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/search'");
+    let response = match engine.render_search(&query.q).await {
+        Ok(output) => Response::builder()
+            .header("content-type", "text/html")
+            .body(Body::from(output))
+            .unwrap(),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/search.json"
+async fn search_json_handler(
+    Query(query): Query<SearchQuery>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/search.json'");
+    let response = match engine.search_json(&query.q).await {
+        Ok(output) => Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(output))
+            .unwrap(),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/tags/:tag"
+async fn tag_handler(
+    axumPath(tag): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/tags/{}'", &tag);
+    let response = match engine.render_tag(&slugify(&tag)).await {
+        Ok(Some(output)) => Response::builder()
+            .header("content-type", "text/html")
+            .body(Body::from(output))
+            .unwrap(),
+        Ok(None) => server_error(StatusCode::NOT_FOUND, anyhow!("Tag: {} was not found", tag)),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/tags/:tag/rss.xml"
+async fn tag_rss_handler(
+    axumPath(tag): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/tags/{}/rss.xml'", &tag);
+    let response = match engine.tag_rss(&slugify(&tag)).await {
+        Ok(Some(rss)) => Response::builder()
+            .header("content-type", "application/rss+xml")
+            .body(Body::from(rss))
+            .unwrap(),
+        Ok(None) => server_error(StatusCode::NOT_FOUND, anyhow!("Tag: {} was not found", tag)),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
+/// Handler for "/:topic/rss.xml"
+async fn topic_rss_handler(
+    axumPath(topic): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/{}/rss.xml'", &topic);
+    let response = match engine.topic_rss(&slugify(&topic)).await {
+        Ok(Some(rss)) => Response::builder()
+            .header("content-type", "application/rss+xml")
+            .body(Body::from(rss))
+            .unwrap(),
+        Ok(None) => server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Topic: {} was not found", topic),
+        ),
+        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    cache_control(&engine, RouteClass::Feed, &uri, response)
+}
+
 /// Handler for "/:topic"
 async fn topic_handler(
     axumPath(topic): axumPath<String>,
+    Query(query): Query<PageQuery>,
     State(engine): State<Arc<Engine>>,
+    uri: Uri,
 ) -> Response<Body> {
     info!("Handling request to '/{}'", &topic);
+    let response = topic_page(engine.clone(), topic, query.page()).await;
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Handler for "/:topic/page/:page"
+async fn topic_page_handler(
+    axumPath((topic, page)): axumPath<(String, String)>,
+    State(engine): State<Arc<Engine>>,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling request to '/{}/page/{}'", &topic, &page);
+    let response = topic_page(engine.clone(), topic, &page).await;
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Validates the requested topic and page number before rendering one of its pages
+async fn topic_page(engine: Arc<Engine>, topic: String, page: &str) -> Response<Body> {
     let topic_slug = slugify(&topic);
     if !engine.topic_slugs.contains(&topic_slug) {
         return server_error(
@@ -97,21 +469,34 @@ async fn topic_handler(
             anyhow!("Topic: {} was not found", topic),
         );
     }
+    let Ok(page) = page.parse() else {
+        return server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Page {} of topic: {} was not found", page, topic_slug),
+        );
+    };
 
-    topic_posts(engine.clone(), topic_slug)
+    topic_posts(engine.clone(), topic_slug, page)
         .await
-        .unwrap_or_else(|err| {
-            println!("wtf {err}");
-            server_error(StatusCode::INTERNAL_SERVER_ERROR, err)
-        })
+        .unwrap_or_else(|err| server_error(StatusCode::INTERNAL_SERVER_ERROR, err))
 }
 
 /// Called by topic_handler to dynamically generate topic pages
-async fn topic_posts(engine: Arc<Engine>, topic_slug: String) -> Result<Response<Body>> {
-    let output = engine
-        .render_topic(&topic_slug)
+async fn topic_posts(
+    engine: Arc<Engine>,
+    topic_slug: String,
+    page: usize,
+) -> Result<Response<Body>> {
+    let Some(output) = engine
+        .render_topic(&topic_slug, page)
         .await
-        .with_context(|| format!("failed to render topic: {}", &topic_slug))?;
+        .with_context(|| format!("failed to render topic: {}", &topic_slug))?
+    else {
+        return Ok(server_error(
+            StatusCode::NOT_FOUND,
+            anyhow!("Page {} of topic: {} was not found", page, topic_slug),
+        ));
+    };
 
     let response = Response::builder()
         .header("content-type", "text/html")
@@ -123,14 +508,11 @@ async fn topic_posts(engine: Arc<Engine>, topic_slug: String) -> Result<Response
 async fn static_assets(
     axumPath(fname): axumPath<String>,
     State(engine): State<Arc<Engine>>,
+    headers: HeaderMap,
+    uri: Uri,
 ) -> Response<Body> {
     info!("Handling static asset: '/static/{}'", &fname);
-    if fname
-        .split("/")
-        .collect::<Vec<&str>>()
-        .iter()
-        .any(|x| x.eq(&".") || x.eq(&".."))
-    {
+    if has_dot_segments(&fname) {
         return server_error(
             StatusCode::FORBIDDEN,
             anyhow!("Attempted use of . or .. paths"),
@@ -140,60 +522,62 @@ async fn static_assets(
     let static_path = Path::new(&engine.app.docpaths.webroot)
         .join("static")
         .join(fname);
-    match File::open(&static_path)
-        .await
-        .with_context(|| format!("failed to open '{}'", &static_path.display()))
-    {
-        Ok(mut f) => {
-            let mut buf = Vec::new();
-            match f
-                .read_to_end(&mut buf)
-                .await
-                .context("failed to read buffer")
-            {
-                Ok(_) => Response::builder()
-                    .header(
-                        "content-type",
-                        mime_from_ext(static_path.extension(), &engine.app.mime_types),
-                    )
-                    .body(Body::from(buf))
-                    .unwrap_or_else(|err| {
-                        server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into())
-                    }),
-                Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
-            }
-        }
-        Err(err) => server_error(StatusCode::NOT_FOUND, err),
+    let response = serve_asset(&static_path, &engine.app.mime_types, &headers).await;
+    cache_control(&engine, RouteClass::Static, &uri, response)
+}
+
+/// Handler for "/.well-known/*fname"
+async fn well_known(
+    axumPath(fname): axumPath<String>,
+    State(engine): State<Arc<Engine>>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response<Body> {
+    info!("Handling well-known resource: '/.well-known/{}'", &fname);
+    if has_dot_segments(&fname) {
+        return server_error(
+            StatusCode::FORBIDDEN,
+            anyhow!("Attempted use of . or .. paths"),
+        );
     }
+
+    let well_known_path = Path::new(&engine.app.docpaths.webroot)
+        .join(".well-known")
+        .join(fname);
+    let response = serve_file(&well_known_path, &engine.app.mime_types, &headers).await;
+    cache_control(&engine, RouteClass::Static, &uri, response)
+}
+
+/// Handler for "/robots.txt"
+async fn robots(State(engine): State<Arc<Engine>>, uri: Uri) -> Response<Body> {
+    info!("Handling request to '/robots.txt'");
+    let response = Response::builder()
+        .header("content-type", "text/plain")
+        .body(Body::from(engine.robots()))
+        .unwrap();
+    cache_control(&engine, RouteClass::Feed, &uri, response)
 }
 
 /// Handler for "/favicon.ico"
-async fn favicon(State(engine): State<Arc<Engine>>) -> Response<Body> {
+async fn favicon(
+    State(engine): State<Arc<Engine>>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response<Body> {
     info!("Handling favicon request");
     let favicon_path = Path::new(&engine.app.docpaths.webroot)
         .join("static")
         .join("favicon.ico");
-    match File::open(&favicon_path).await {
-        Ok(mut f) => {
-            let mut buf = Vec::new();
-            match f.read_to_end(&mut buf).await {
-                Ok(_) => Response::builder()
-                    .header("content-type", "image/vnd.microsoft.icon")
-                    .body(Body::from(buf))
-                    .unwrap_or_else(|err| {
-                        server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into())
-                    }),
-                Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into()),
-            }
-        }
-        Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into()),
-    }
+    let response = assets::serve_file(&favicon_path, "image/vnd.microsoft.icon", &headers).await;
+    cache_control(&engine, RouteClass::Static, &uri, response)
 }
 
 /// Handler for "/:topic/ext/*fname"
 async fn topic_assets(
     axumPath((topic, fname)): axumPath<(String, String)>,
     State(engine): State<Arc<Engine>>,
+    headers: HeaderMap,
+    uri: Uri,
 ) -> Response<Body> {
     info!("Handling static asset: '/{}/ext/{}'", &topic, &fname);
     let topic_slug = slugify(&topic);
@@ -204,12 +588,7 @@ async fn topic_assets(
         );
     }
 
-    if fname
-        .split("/")
-        .collect::<Vec<&str>>()
-        .iter()
-        .any(|x| x.eq(&".") || x.eq(&".."))
-    {
+    if has_dot_segments(&fname) {
         return server_error(
             StatusCode::FORBIDDEN,
             anyhow!("Attempted use of . or .. paths"),
@@ -220,41 +599,43 @@ async fn topic_assets(
         .join(topic)
         .join("ext")
         .join(fname);
+    let response = serve_asset(&topic_asset_path, &engine.app.mime_types, &headers).await;
+    cache_control(&engine, RouteClass::Ext, &uri, response)
+}
 
-    match File::open(&topic_asset_path)
-        .await
-        .with_context(|| format!("failed to open '{}'", &topic_asset_path.display()))
-    {
-        Ok(mut f) => {
-            let mut buf = Vec::new();
-            match f
-                .read_to_end(&mut buf)
-                .await
-                .context("failed to read buffer")
-            {
-                Ok(_) => Response::builder()
-                    .header(
-                        "content-type",
-                        mime_from_ext(topic_asset_path.extension(), &engine.app.mime_types),
-                    )
-                    .body(Body::from(buf))
-                    .unwrap_or_else(|err| {
-                        server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into())
-                    }),
-                Err(err) => server_error(StatusCode::INTERNAL_SERVER_ERROR, err),
-            }
-        }
-        Err(err) => server_error(StatusCode::NOT_FOUND, err),
-    }
+/// Returns whether a requested path contains `.` or `..` segments, which are refused for all file routes.
+fn has_dot_segments(fname: &str) -> bool {
+    fname.split('/').any(|x| x == "." || x == "..")
+}
+
+/// Streams a file from disk into a response, with its MIME type from the user's config.
+async fn serve_file(
+    path: &Path,
+    mime_types: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    let content_type = mime_from_ext(path.extension(), mime_types);
+    assets::serve_file(path, &content_type, headers).await
+}
+
+/// Streams a file as [`serve_file`] does, or its precompressed sibling when the client accepts it.
+async fn serve_asset(
+    path: &Path,
+    mime_types: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    let content_type = mime_from_ext(path.extension(), mime_types);
+    assets::serve_precompressed(path, &content_type, headers).await
 }
 
 /// Handler for "/:topic/posts/:post"
 async fn post_handler(
     axumPath((topic, post)): axumPath<(String, String)>,
     State(engine): State<Arc<Engine>>,
+    uri: Uri,
 ) -> Response<Body> {
     info!("Handling topic post: '/{}/posts/{}'", &topic, &post);
-    match engine
+    let response = match engine
         .render_post(&slugify(&topic), &post)
         .await
         .with_context(|| format!("failed to render: '{}/posts/{}'", topic, post))
@@ -264,7 +645,30 @@ async fn post_handler(
             .body(Body::from(output))
             .unwrap_or_else(|err| server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into())),
         Err(err) => server_error(StatusCode::NOT_FOUND, err),
+    };
+    cache_control(&engine, RouteClass::Page, &uri, response)
+}
+
+/// Sets `Cache-Control` on successful responses, following the `[cache]` policy for `uri`.
+fn cache_control(
+    engine: &Engine,
+    class: RouteClass,
+    uri: &Uri,
+    mut response: Response<Body>,
+) -> Response<Body> {
+    let status = response.status();
+    if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+        return response;
     }
+
+    let policy = engine.app.cache.policy(class, uri.path());
+    if let Some(value) = policy
+        .header_value()
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+    {
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+    response
 }
 
 /// Builds server error responses and logs originating error
@@ -283,6 +687,7 @@ mod tests {
 
     use super::*;
     use crate::config::AppConfig;
+    use crate::testing;
     use hyper::StatusCode;
     use reqwest::Client;
     use tokio::sync::oneshot::channel;
@@ -290,10 +695,18 @@ mod tests {
     #[tokio::test]
     async fn check_all_handlers() {
         let dir = tempfile::tempdir().unwrap();
-        let mut src: &[u8] = b"Site Name\nAuthor Name\nhttps://some.special.site\nOne, Two, Three, And More\nadmin\n";
-        let app = AppConfig::generate(&dir, &mut src).unwrap();
-        let engine = Engine::new(app);
-        let engine = Arc::new(engine);
+        let mut app = testing::site_at(
+            &dir,
+            "https://some.special.site",
+            &["One", "Two", "Three", "And More"],
+        );
+        app.cache = toml::from_str(
+            "pages = { no_cache = true }\nfeeds = { max_age = 900 }\nstatic = { max_age = 86400 }\n",
+        )
+        .unwrap();
+        let engine = Engine::new(app).unwrap();
+        let addr = format!("{}:{}", engine.app.server.bind, engine.app.server.port);
+        let engine = SharedEngine::new(engine);
 
         let index_page = r#"
 ### Main Page
@@ -303,7 +716,9 @@ Main Important Test
         let mut f = File::create(dir.path().join("site/webroot/main/posts/index.md")).unwrap();
         f.write_all(index_page.as_bytes()).unwrap();
 
-        let topic_page = r#"
+        let topic_page = r#"+++
+tags = ["Routing"]
++++
 ### One Section
 
 One Important Test
@@ -326,8 +741,13 @@ One Important Test
         let mut f = File::create(dir.path().join("site/webroot/static/favicon.ico")).unwrap();
         f.write_all(favicon).unwrap();
 
+        let well_known = b"Contact: mailto:admin@some.special.site\n";
+
+        std::fs::create_dir_all(dir.path().join("site/webroot/.well-known")).unwrap();
+        let mut f = File::create(dir.path().join("site/webroot/.well-known/security.txt")).unwrap();
+        f.write_all(well_known).unwrap();
+
         let router = router(engine.clone());
-        let addr = format!("{}:{}", engine.app.server.bind, engine.app.server.port);
         let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
         let server = axum::serve(listener, router);
 
@@ -346,37 +766,217 @@ One Important Test
         let index_request_url = "http://localhost:9090";
         let post_request_url = "http://localhost:9090/one/posts/index";
         let topic_request_url = "http://localhost:9090/one";
+        let topic_page_request_url = "http://localhost:9090/one/page/1";
         let topic_asset_request_url = "http://localhost:9090/one/ext/one-static";
         let static_asset_request_url = "http://localhost:9090/static/main-static";
         let favicon_request_url = "http://localhost:9090/favicon.ico";
         let bad_topic_request_url = "http://localhost:9090/badtopic";
         let bad_post_request_url = "http://localhost:9090/one/posts/nope";
         let bad_static_request_url = "http://localhost:9090/static/nope";
+        let bad_page_request_url = "http://localhost:9090/one/page/2";
+        let bad_index_page_request_url = "http://localhost:9090/?page=2";
+        let bad_page_number_request_url = "http://localhost:9090/one/page/two";
+        let bad_index_page_number_request_url = "http://localhost:9090/page/-1";
+        let bad_page_query_request_url = "http://localhost:9090/one?page=x";
         let rss_request_url = "http://localhost:9090/rss.xml";
+        let atom_request_url = "http://localhost:9090/atom.xml";
+        let json_feed_request_url = "http://localhost:9090/feed.json";
+        let topic_rss_request_url = "http://localhost:9090/one/rss.xml";
+        let sitemap_request_url = "http://localhost:9090/sitemap.xml";
+        let robots_request_url = "http://localhost:9090/robots.txt";
+        let tags_request_url = "http://localhost:9090/tags";
+        let archive_request_url = "http://localhost:9090/archive";
+        let bad_archive_request_url = "http://localhost:9090/archive/1999/01";
+        let bad_archive_year_request_url = "http://localhost:9090/archive/page";
+        let tag_request_url = "http://localhost:9090/tags/routing";
+        let tag_rss_request_url = "http://localhost:9090/tags/routing/rss.xml";
+        let bad_tag_request_url = "http://localhost:9090/tags/nope";
+        let well_known_request_url = "http://localhost:9090/.well-known/security.txt";
+        let bad_well_known_request_url =
+            "http://localhost:9090/.well-known/..%2fstatic%2fmain-static";
+        let bad_sitemap_part_request_url = "http://localhost:9090/sitemap/1.xml";
+        let bad_topic_rss_request_url = "http://localhost:9090/main/rss.xml";
+        let search_request_url = "http://localhost:9090/search?q=routing";
+        let search_json_request_url = "http://localhost:9090/search.json?q=routing";
 
         let client = Client::new();
 
         let index_resp = client.get(index_request_url).send().await.unwrap();
         let post_resp = client.get(post_request_url).send().await.unwrap();
         let topic_resp = client.get(topic_request_url).send().await.unwrap();
+        let topic_page_resp = client.get(topic_page_request_url).send().await.unwrap();
         let topic_asset_resp = client.get(topic_asset_request_url).send().await.unwrap();
         let static_asset_resp = client.get(static_asset_request_url).send().await.unwrap();
         let favicon_resp = client.get(favicon_request_url).send().await.unwrap();
         let rss_resp = client.get(rss_request_url).send().await.unwrap();
+        let atom_resp = client.get(atom_request_url).send().await.unwrap();
+        let json_feed_resp = client.get(json_feed_request_url).send().await.unwrap();
+        let topic_rss_resp = client.get(topic_rss_request_url).send().await.unwrap();
+        let sitemap_resp = client.get(sitemap_request_url).send().await.unwrap();
+        let robots_resp = client.get(robots_request_url).send().await.unwrap();
+        let tags_resp = client.get(tags_request_url).send().await.unwrap();
+        let archive_resp = client.get(archive_request_url).send().await.unwrap();
+        let bad_archive_resp = client.get(bad_archive_request_url).send().await.unwrap();
+        let bad_archive_year_resp = client
+            .get(bad_archive_year_request_url)
+            .send()
+            .await
+            .unwrap();
+        let tag_resp = client.get(tag_request_url).send().await.unwrap();
+        let tag_rss_resp = client.get(tag_rss_request_url).send().await.unwrap();
+        let bad_tag_resp = client.get(bad_tag_request_url).send().await.unwrap();
+        let well_known_resp = client.get(well_known_request_url).send().await.unwrap();
+        let bad_well_known_resp = client.get(bad_well_known_request_url).send().await.unwrap();
+        let bad_sitemap_part_resp = client
+            .get(bad_sitemap_part_request_url)
+            .send()
+            .await
+            .unwrap();
+        let bad_topic_rss_resp = client.get(bad_topic_rss_request_url).send().await.unwrap();
+        let search_resp = client.get(search_request_url).send().await.unwrap();
+        let topic_asset_range_resp = client
+            .get(topic_asset_request_url)
+            .header("range", "bytes=4-9")
+            .send()
+            .await
+            .unwrap();
+        let search_json_resp = client.get(search_json_request_url).send().await.unwrap();
         assert_eq!(index_resp.status(), StatusCode::OK);
         assert_eq!(post_resp.status(), StatusCode::OK);
         assert_eq!(topic_resp.status(), StatusCode::OK);
+        assert_eq!(topic_page_resp.status(), StatusCode::OK);
         assert_eq!(topic_asset_resp.status(), StatusCode::OK);
         assert_eq!(static_asset_resp.status(), StatusCode::OK);
         assert_eq!(favicon_resp.status(), StatusCode::OK);
         assert_eq!(rss_resp.status(), StatusCode::OK);
+        assert_eq!(atom_resp.status(), StatusCode::OK);
+        assert_eq!(atom_resp.headers()["content-type"], "application/atom+xml");
+        assert_eq!(index_resp.headers()["cache-control"], "no-cache");
+        assert_eq!(rss_resp.headers()["cache-control"], "max-age=900");
+        assert_eq!(
+            static_asset_resp.headers()["cache-control"],
+            "max-age=86400"
+        );
+        assert!(topic_asset_resp.headers().get("cache-control").is_none());
+        assert_eq!(json_feed_resp.status(), StatusCode::OK);
+        assert_eq!(
+            json_feed_resp.headers()["content-type"],
+            "application/feed+json"
+        );
+        assert_eq!(topic_rss_resp.status(), StatusCode::OK);
+        assert_eq!(bad_topic_rss_resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(sitemap_resp.status(), StatusCode::OK);
+        assert_eq!(bad_sitemap_part_resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(bad_well_known_resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(well_known_resp.status(), StatusCode::OK);
+        assert_eq!(well_known_resp.bytes().await.unwrap().as_ref(), well_known);
+        assert_eq!(tags_resp.status(), StatusCode::OK);
+        assert_eq!(archive_resp.status(), StatusCode::OK);
+        assert_eq!(bad_archive_resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(bad_archive_year_resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(tag_resp.status(), StatusCode::OK);
+        assert_eq!(tag_rss_resp.status(), StatusCode::OK);
+        assert_eq!(bad_tag_resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(topic_asset_range_resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(topic_asset_range_resp.headers()["content-length"], "6");
+        assert_eq!(topic_asset_range_resp.text().await.unwrap(), "Static");
+        assert_eq!(search_resp.status(), StatusCode::OK);
+        assert_eq!(search_json_resp.status(), StatusCode::OK);
+        assert_eq!(
+            search_json_resp.headers()["content-type"],
+            "application/json"
+        );
+        assert_eq!(robots_resp.status(), StatusCode::OK);
+        assert!(robots_resp
+            .text()
+            .await
+            .unwrap()
+            .contains("Sitemap: https://some.special.site/sitemap.xml"));
 
         let bad_topic_resp = client.get(bad_topic_request_url).send().await.unwrap();
         let bad_post_resp = client.get(bad_post_request_url).send().await.unwrap();
         let bad_static_resp = client.get(bad_static_request_url).send().await.unwrap();
+        let bad_page_resp = client.get(bad_page_request_url).send().await.unwrap();
+        let bad_index_page_resp = client.get(bad_index_page_request_url).send().await.unwrap();
+        assert_eq!(bad_page_resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(bad_index_page_resp.status(), StatusCode::NOT_FOUND);
+        for url in [
+            bad_page_number_request_url,
+            bad_index_page_number_request_url,
+            bad_page_query_request_url,
+        ] {
+            let resp = client.get(url).send().await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", url);
+        }
         assert_eq!(bad_topic_resp.status(), StatusCode::NOT_FOUND);
         assert_eq!(bad_post_resp.status(), StatusCode::NOT_FOUND);
         assert_eq!(bad_static_resp.status(), StatusCode::NOT_FOUND);
+        assert!(bad_static_resp.headers().get("cache-control").is_none());
+
+        let _ = tx.send(());
+    }
+
+    #[tokio::test]
+    async fn check_compression() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testing::site_at(&dir, "https://some.special.site", &["One"]);
+        config.server.port = 9092;
+        let addr = format!("{}:{}", config.server.bind, config.server.port);
+        let engine = SharedEngine::new(Engine::new(config).unwrap());
+
+        let webroot = dir.path().join("site/webroot");
+        std::fs::write(
+            webroot.join("main/posts/index.md"),
+            "Compressible text. ".repeat(200),
+        )
+        .unwrap();
+        std::fs::write(webroot.join("static/app.js"), "plain").unwrap();
+        std::fs::write(webroot.join("static/app.js.gz"), "gzipped").unwrap();
+        let css = "body { color: black; } ".repeat(200);
+        std::fs::write(webroot.join("static/site.css"), &css).unwrap();
+
+        let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+        let server = axum::serve(listener, router(engine));
+        let (tx, rx) = channel::<()>();
+        tokio::spawn(async move {
+            let graceful = server.with_graceful_shutdown(async {
+                rx.await.ok();
+            });
+            if let Err(e) = graceful.await {
+                println!("Encountered error: {}", e)
+            }
+        });
+
+        let client = Client::new();
+        let get = |path: &str, encoding: &'static str| {
+            client
+                .get(format!("http://localhost:9092{}", path))
+                .header("accept-encoding", encoding)
+                .send()
+        };
+
+        let index_resp = get("/", "br").await.unwrap();
+        assert_eq!(index_resp.headers()["content-encoding"], "br");
+        let index_resp = get("/", "gzip").await.unwrap();
+        assert_eq!(index_resp.headers()["content-encoding"], "gzip");
+        let index_resp = get("/", "identity").await.unwrap();
+        assert!(!index_resp.headers().contains_key("content-encoding"));
+        let robots_resp = get("/robots.txt", "gzip").await.unwrap();
+        assert!(!robots_resp.headers().contains_key("content-encoding"));
+
+        let asset_resp = get("/static/app.js", "gzip").await.unwrap();
+        assert_eq!(asset_resp.headers()["content-encoding"], "gzip");
+        assert_eq!(asset_resp.bytes().await.unwrap().as_ref(), b"gzipped");
+        let asset_resp = get("/static/app.js", "identity").await.unwrap();
+        assert_eq!(asset_resp.bytes().await.unwrap().as_ref(), b"plain");
+
+        // Files without a precompressed sibling keep the bytes their ETag describes.
+        let plain_resp = get("/static/site.css", "identity").await.unwrap();
+        let etag = plain_resp.headers()["etag"].clone();
+        let css_resp = get("/static/site.css", "gzip, br").await.unwrap();
+        assert!(!css_resp.headers().contains_key("content-encoding"));
+        assert_eq!(css_resp.headers()["etag"], etag);
+        assert_eq!(css_resp.bytes().await.unwrap().as_ref(), css.as_bytes());
 
         let _ = tx.send(());
     }
@@ -384,8 +984,9 @@ One Important Test
     #[tokio::test]
     async fn check_custom_config() {
         let app = AppConfig::from_path("test_files/test-config.toml").unwrap();
-        let engine = Engine::new(app);
-        let engine = Arc::new(engine);
+        let engine = Engine::new(app).unwrap();
+        let addr = format!("{}:{}", engine.app.server.bind, engine.app.server.port);
+        let engine = SharedEngine::new(engine);
 
         let router = router(engine.clone());
 
@@ -395,7 +996,6 @@ One Important Test
         let gallery_request_url = "http://localhost:8901/gallery";
         let rss_request_url = "http://localhost:8901/rss.xml";
 
-        let addr = format!("{}:{}", engine.app.server.bind, engine.app.server.port);
         let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
         let server = axum::serve(listener, router);
 
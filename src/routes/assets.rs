@@ -0,0 +1,464 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Streams files from disk for the asset routes, with `Range` requests and conditional `GET`s.
+//!
+//! Files under `static/` and `{topic}/ext/` may have precompressed `.br` and `.gz` siblings, which are
+//! served in their place to clients that accept them.
+
+use std::ffi::OsString;
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use log::debug;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use super::server_error;
+
+/// Content codings of precompressed siblings with their extensions, in order of preference.
+const PRECOMPRESSED: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// Format of HTTP dates, as used by `Last-Modified` and `If-Modified-Since`.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Validators of a file's current contents.
+#[derive(Debug, PartialEq)]
+struct Validators {
+    etag: String,
+    /// Modification time, truncated to the whole seconds HTTP dates can express.
+    last_modified: DateTime<Utc>,
+}
+
+impl Validators {
+    fn new(len: u64, modified: std::time::SystemTime) -> Validators {
+        let nanos = modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let last_modified = DateTime::<Utc>::from(modified);
+        Validators {
+            etag: format!("\"{:x}-{:x}\"", nanos, len),
+            last_modified: DateTime::from_timestamp(last_modified.timestamp(), 0)
+                .unwrap_or(last_modified),
+        }
+    }
+
+    /// Whether the client's cached copy is current, following the precedence of RFC 9110 §13.2.2.
+    fn not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(value) = header_str(headers, header::IF_NONE_MATCH) {
+            return value
+                .split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.etag);
+        }
+
+        header_str(headers, header::IF_MODIFIED_SINCE)
+            .and_then(parse_http_date)
+            .is_some_and(|since| self.last_modified <= since)
+    }
+
+    /// Whether a `Range` may be honoured, which `If-Range` limits to an unchanged file.
+    fn range_applies(&self, headers: &HeaderMap) -> bool {
+        match header_str(headers, header::IF_RANGE) {
+            None => true,
+            Some(value) if value.starts_with('"') => value == self.etag,
+            Some(value) => parse_http_date(value).is_some_and(|date| date == self.last_modified),
+        }
+    }
+}
+
+/// A `Range` header as it applies to a file of a given length.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// The whole file, as when no usable range was requested.
+    Full,
+    /// Inclusive start and end offsets.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// Resolves a single `bytes=` range against a file of `len` bytes.
+    ///
+    /// Malformed values and multiple ranges are ignored in favour of the whole file, as RFC 9110
+    /// allows.
+    fn parse(value: &str, len: u64) -> ByteRange {
+        let Some(spec) = value.trim().strip_prefix("bytes=") else {
+            return ByteRange::Full;
+        };
+        if spec.contains(',') {
+            return ByteRange::Full;
+        }
+        let Some((start, end)) = spec.trim().split_once('-') else {
+            return ByteRange::Full;
+        };
+
+        let (start, end) = match (start.trim(), end.trim()) {
+            ("", suffix) => match suffix.parse::<u64>() {
+                Ok(0) => return ByteRange::Unsatisfiable,
+                Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+                Err(_) => return ByteRange::Full,
+            },
+            (start, "") => match start.parse::<u64>() {
+                Ok(start) => (start, len.saturating_sub(1)),
+                Err(_) => return ByteRange::Full,
+            },
+            (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+                (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+                _ => return ByteRange::Full,
+            },
+        };
+
+        if len == 0 || start >= len {
+            ByteRange::Unsatisfiable
+        } else {
+            ByteRange::Partial(start, end)
+        }
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Whether the request's `Accept-Encoding` allows the `encoding` content coding.
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    let Some(value) = header_str(headers, header::ACCEPT_ENCODING) else {
+        return false;
+    };
+
+    let mut wildcard = false;
+    for entry in value.split(',') {
+        let mut params = entry.split(';');
+        let coding = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if coding.eq_ignore_ascii_case(encoding) {
+            return quality > 0.0;
+        }
+        if coding == "*" {
+            wildcard = quality > 0.0;
+        }
+    }
+    wildcard
+}
+
+/// Streams a file as [`serve_file`] does, preferring a `.br` or `.gz` sibling the client accepts.
+///
+/// `content_type` describes the original file, and is kept for its compressed siblings.
+pub(super) async fn serve_precompressed(
+    path: &Path,
+    content_type: &str,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    let mut siblings = Vec::new();
+    for (encoding, ext) in PRECOMPRESSED {
+        let mut sibling = OsString::from(path.as_os_str());
+        sibling.push(".");
+        sibling.push(ext);
+        let sibling = PathBuf::from(sibling);
+        if tokio::fs::metadata(&sibling)
+            .await
+            .is_ok_and(|metadata| metadata.is_file())
+        {
+            siblings.push((encoding, sibling));
+        }
+    }
+    if siblings.is_empty() {
+        return serve_file(path, content_type, headers).await;
+    }
+
+    let mut response = match siblings
+        .iter()
+        .find(|(encoding, _)| accepts_encoding(headers, encoding))
+    {
+        Some((encoding, sibling)) => {
+            debug!("Serving '{}' for '{}'", sibling.display(), path.display());
+            let mut response = serve_file(sibling, content_type, headers).await;
+            let status = response.status();
+            if status.is_success() || status == StatusCode::NOT_MODIFIED {
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+            }
+            response
+        }
+        None => serve_file(path, content_type, headers).await,
+    };
+    response.headers_mut().append(
+        header::VARY,
+        HeaderValue::from_static(header::ACCEPT_ENCODING.as_str()),
+    );
+    response
+}
+
+/// Streams a file from disk as `content_type`, honouring the request's `Range` and conditional headers.
+pub(super) async fn serve_file(
+    path: &Path,
+    content_type: &str,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    let mut file = match File::open(path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return server_error(
+                StatusCode::NOT_FOUND,
+                anyhow!("failed to open '{}': {}", path.display(), err),
+            );
+        }
+        Err(err) => {
+            return server_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow!("failed to open '{}': {}", path.display(), err),
+            );
+        }
+    };
+    let metadata = match file.metadata().await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => {
+            return server_error(
+                StatusCode::NOT_FOUND,
+                anyhow!("'{}' is not a file", path.display()),
+            );
+        }
+        Err(err) => return server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into()),
+    };
+
+    let len = metadata.len();
+    let validators = Validators::new(len, metadata.modified().unwrap_or(UNIX_EPOCH));
+    let builder = Response::builder()
+        .header(header::ETAG, &validators.etag)
+        .header(
+            header::LAST_MODIFIED,
+            validators.last_modified.format(HTTP_DATE).to_string(),
+        )
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    if validators.not_modified(headers) {
+        debug!("'{}' not modified", path.display());
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let range = match header_str(headers, header::RANGE) {
+        Some(value) if validators.range_applies(headers) => ByteRange::parse(value, len),
+        _ => ByteRange::Full,
+    };
+    let builder = builder.header(header::CONTENT_TYPE, content_type);
+    let response = match range {
+        ByteRange::Full => builder
+            .header(header::CONTENT_LENGTH, len)
+            .body(Body::from_stream(ReaderStream::new(file))),
+        ByteRange::Partial(start, end) => {
+            debug!("Serving bytes {}-{} of '{}'", start, end, path.display());
+            if let Err(err) = file.seek(SeekFrom::Start(start)).await {
+                return server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into());
+            }
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, len),
+                )
+                .header(header::CONTENT_LENGTH, end - start + 1)
+                .body(Body::from_stream(ReaderStream::new(
+                    file.take(end - start + 1),
+                )))
+        }
+        ByteRange::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Body::empty()),
+    };
+
+    response.unwrap_or_else(|err| server_error(StatusCode::INTERNAL_SERVER_ERROR, err.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_byte_ranges() {
+        assert_eq!(ByteRange::parse("bytes=0-4", 10), ByteRange::Partial(0, 4));
+        assert_eq!(ByteRange::parse("bytes=5-", 10), ByteRange::Partial(5, 9));
+        assert_eq!(ByteRange::parse("bytes=-3", 10), ByteRange::Partial(7, 9));
+        assert_eq!(ByteRange::parse("bytes=-30", 10), ByteRange::Partial(0, 9));
+        assert_eq!(ByteRange::parse("bytes=8-20", 10), ByteRange::Partial(8, 9));
+        assert_eq!(ByteRange::parse("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(ByteRange::parse("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(ByteRange::parse("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(ByteRange::parse("bytes=4-2", 10), ByteRange::Full);
+        assert_eq!(ByteRange::parse("bytes=0-1,4-5", 10), ByteRange::Full);
+        assert_eq!(ByteRange::parse("items=0-1", 10), ByteRange::Full);
+    }
+
+    fn header_map(headers: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    async fn get(path: &Path, headers: &[(header::HeaderName, &str)]) -> Response<Body> {
+        serve_file(path, "text/plain", &header_map(headers)).await
+    }
+
+    async fn body(response: Response<Body>) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn serve_ranges_and_conditionals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asset.txt");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let full = get(&path, &[]).await;
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.headers()[header::CONTENT_LENGTH], "10");
+        assert_eq!(full.headers()[header::ACCEPT_RANGES], "bytes");
+        let etag = full.headers()[header::ETAG].to_str().unwrap().to_owned();
+        let modified = full.headers()[header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(body(full).await, b"0123456789");
+
+        let partial = get(&path, &[(header::RANGE, "bytes=2-5")]).await;
+        assert_eq!(partial.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(partial.headers()[header::CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(partial.headers()[header::CONTENT_LENGTH], "4");
+        assert_eq!(body(partial).await, b"2345");
+
+        let unsatisfiable = get(&path, &[(header::RANGE, "bytes=20-")]).await;
+        assert_eq!(unsatisfiable.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(unsatisfiable.headers()[header::CONTENT_RANGE], "bytes */10");
+
+        let stale = get(
+            &path,
+            &[(header::RANGE, "bytes=2-5"), (header::IF_RANGE, "\"old\"")],
+        )
+        .await;
+        assert_eq!(stale.status(), StatusCode::OK);
+        let fresh = get(
+            &path,
+            &[(header::RANGE, "bytes=2-5"), (header::IF_RANGE, &etag)],
+        )
+        .await;
+        assert_eq!(fresh.status(), StatusCode::PARTIAL_CONTENT);
+
+        let cached = get(&path, &[(header::IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert!(body(cached).await.is_empty());
+        let cached = get(&path, &[(header::IF_MODIFIED_SINCE, &modified)]).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        let changed = get(
+            &path,
+            &[
+                (header::IF_NONE_MATCH, "\"old\""),
+                (header::IF_MODIFIED_SINCE, &modified),
+            ],
+        )
+        .await;
+        assert_eq!(changed.status(), StatusCode::OK);
+        let old = get(
+            &path,
+            &[(header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")],
+        )
+        .await;
+        assert_eq!(old.status(), StatusCode::OK);
+
+        assert_eq!(
+            get(&dir.path().join("nope"), &[]).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(get(dir.path(), &[]).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn accept_encoding_negotiation() {
+        let accepts = |value: &str, encoding: &str| {
+            accepts_encoding(&header_map(&[(header::ACCEPT_ENCODING, value)]), encoding)
+        };
+        assert!(accepts("gzip, deflate, br", "br"));
+        assert!(accepts("GZIP;q=0.5", "gzip"));
+        assert!(!accepts("gzip;q=0", "gzip"));
+        assert!(accepts("*", "br"));
+        assert!(!accepts("*, br;q=0", "br"));
+        assert!(!accepts("identity", "gzip"));
+        assert!(!accepts_encoding(&HeaderMap::new(), "gzip"));
+    }
+
+    #[tokio::test]
+    async fn serve_precompressed_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.js");
+        std::fs::write(&path, b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzipped").unwrap();
+        std::fs::write(dir.path().join("app.js.br"), b"brotli").unwrap();
+        let serve = |value: &'static str| {
+            let path = path.clone();
+            async move {
+                let headers = header_map(&[(header::ACCEPT_ENCODING, value)]);
+                serve_precompressed(&path, "application/javascript", &headers).await
+            }
+        };
+
+        let response = serve("gzip, br").await;
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/javascript"
+        );
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(body(response).await, b"brotli");
+
+        let response = serve("gzip").await;
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(body(response).await, b"gzipped");
+
+        let response = serve("identity").await;
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(body(response).await, b"plain");
+
+        std::fs::write(dir.path().join("plain.txt"), b"text").unwrap();
+        let headers = header_map(&[(header::ACCEPT_ENCODING, "gzip")]);
+        let response =
+            serve_precompressed(&dir.path().join("plain.txt"), "text/plain", &headers).await;
+        assert!(!response.headers().contains_key(header::VARY));
+        assert_eq!(body(response).await, b"text");
+    }
+}
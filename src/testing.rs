@@ -0,0 +1,35 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides the site fixtures shared by unit tests across modules.
+
+use std::path::Path;
+
+use super::config::{AppConfig, Seed};
+
+/// Base URL of the sites generated by [`site`].
+pub(crate) const URL: &str = "https://special.example.site";
+
+/// Generates a site in `dir` with `topics`, just as `arse new` does from a seed file.
+pub(crate) fn site<P: AsRef<Path>>(dir: P, topics: &[&str]) -> AppConfig {
+    site_at(dir, URL, topics)
+}
+
+/// Generates a site in `dir` with `topics`, served from the base URL `url`.
+pub(crate) fn site_at<P: AsRef<Path>>(dir: P, url: &str, topics: &[&str]) -> AppConfig {
+    let seed = Seed {
+        name: Some("Site Name".to_owned()),
+        author: Some("Author Name".to_owned()),
+        url: Some(url.to_owned()),
+        topics: Some(topics.iter().map(|topic| topic.to_string()).collect()),
+        ..Seed::default()
+    };
+    AppConfig::generate_from(seed, dir, None::<&mut &[u8]>).unwrap()
+}
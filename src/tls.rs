@@ -0,0 +1,355 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides HTTPS termination for `[server.tls]`.
+//!
+//! - HTTP/2 and HTTP/1.1 are offered over ALPN.
+//! - The certificate and key are reloaded when either changes on disk, and on `SIGHUP` on UNIX-like
+//!   systems, without interrupting established connections.
+//! - An optional plain HTTP listener redirects every request to HTTPS.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use axum::http::uri::Authority;
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use log::{debug, error, info, trace, warn};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::task::JoinSet;
+
+use super::config::Tls;
+use super::listen::{self, Listener};
+use super::watch::{sighup, DEBOUNCE};
+use super::{Context, Result};
+
+/// Serves `router` over HTTPS on the TCP `listeners`, along with any redirect listener.
+///
+/// Unix domain sockets are served over plain HTTP, since they are only reachable locally,
+/// such as by a reverse proxy.
+pub(crate) async fn serve(tls: &Tls, listeners: Vec<Listener>, router: Router) -> Result<()> {
+    let config = load(tls).await?;
+    info!("TLS certificate loaded from '{}'", tls.cert);
+
+    let reloaded = config.clone();
+    let watched = tls.clone();
+    tokio::spawn(async move {
+        if let Err(err) = watch(reloaded, watched).await {
+            error!("Stopped watching for certificate changes: {:#}", err)
+        }
+    });
+
+    if let Some(port) = tls.redirect_port {
+        match https_addr(&listeners) {
+            Some(https) => {
+                let addr = SocketAddr::new(https.ip(), port);
+                let listener = tokio::net::TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("failed to bind redirect listener on '{}'", addr))?;
+                info!("Redirecting HTTP requests on: {}", addr);
+                tokio::spawn(async move {
+                    if let Err(err) = axum::serve(listener, redirect_router(https.port())).await {
+                        error!("Unhandled redirect server error: {}", err)
+                    }
+                });
+            }
+            None => warn!("Not redirecting HTTP requests, as no TCP socket is serving HTTPS"),
+        }
+    }
+
+    let mut servers = JoinSet::new();
+    for listener in listeners {
+        let addr = listener.to_string();
+        match listener {
+            Listener::Tcp(tcp) => {
+                info!("Running HTTPS server on: {}", addr);
+                let server = axum_server::from_tcp_rustls(tcp, config.clone());
+                servers.spawn(server.serve(router.clone().into_make_service()));
+            }
+            #[cfg(target_family = "unix")]
+            unix => {
+                warn!("Running server without TLS on: {}", addr);
+                servers.spawn(unix.serve(router.clone()));
+            }
+        }
+    }
+    listen::wait(servers).await;
+
+    Ok(())
+}
+
+/// Returns the address of the first TCP listener, which serves HTTPS and is the target of redirects.
+fn https_addr(listeners: &[Listener]) -> Option<SocketAddr> {
+    listeners.iter().find_map(|listener| match listener {
+        Listener::Tcp(tcp) => tcp.local_addr().ok(),
+        #[cfg(target_family = "unix")]
+        Listener::Unix(_) => None,
+    })
+}
+
+/// Loads the certificate chain and private key named by `tls`.
+async fn load(tls: &Tls) -> Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&tls.cert, &tls.key)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to load TLS certificate '{}' with key '{}'",
+                tls.cert, tls.key
+            )
+        })
+}
+
+/// Certificate and key paths being watched, resolved against their canonicalized directories
+/// so they can be compared with event paths even when the files themselves are replaced.
+fn watched_files(tls: &Tls) -> Result<Vec<PathBuf>> {
+    [&tls.cert, &tls.key]
+        .into_iter()
+        .map(|file| {
+            let path = Path::new(file);
+            let name = path
+                .file_name()
+                .with_context(|| format!("'{}' is not a file", file))?;
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let dir = dir
+                .canonicalize()
+                .with_context(|| format!("failed to resolve '{}'", dir.display()))?;
+            Ok(dir.join(name))
+        })
+        .collect()
+}
+
+/// Reloads `config` whenever the certificate or key changes, until an unrecoverable error occurs.
+async fn watch(config: RustlsConfig, tls: Tls) -> Result<()> {
+    let (tx, mut rx) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let _ = tx.send(res);
+    })
+    .context("failed to create certificate watcher")?;
+
+    let files = watched_files(&tls)?;
+    let mut dirs: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+    dirs.dedup();
+    for dir in dirs {
+        info!("Watching '{}' for certificate changes", dir.display());
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch '{}'", dir.display()))?;
+    }
+
+    #[cfg(target_family = "unix")]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .context("failed to listen for SIGHUP")?;
+    #[cfg(not(target_family = "unix"))]
+    let mut hangup = ();
+
+    loop {
+        tokio::select! {
+            res = rx.recv() => match res {
+                Some(res) => if !settle(&files, res, &mut rx).await {
+                    continue;
+                },
+                None => return Ok(()),
+            },
+            _ = sighup(&mut hangup) => info!("Received SIGHUP"),
+        }
+
+        match config.reload_from_pem_file(&tls.cert, &tls.key).await {
+            Ok(()) => info!("TLS certificate reloaded from '{}'", tls.cert),
+            Err(err) => error!(
+                "Failed to reload TLS certificate, continuing with the previous one: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Waits out a burst of events following `first`, returning whether any touched `files`.
+async fn settle(
+    files: &[PathBuf],
+    first: notify::Result<Event>,
+    rx: &mut UnboundedReceiver<notify::Result<Event>>,
+) -> bool {
+    tokio::time::sleep(DEBOUNCE).await;
+    let mut changed = false;
+    for res in std::iter::once(first).chain(std::iter::from_fn(|| rx.try_recv().ok())) {
+        match res {
+            Ok(event) => {
+                trace!("Certificate event: {:?}", event);
+                changed |= !matches!(event.kind, EventKind::Access(_))
+                    && event.paths.iter().any(|path| files.contains(path));
+            }
+            Err(err) => error!("Certificate watch error: {}", err),
+        }
+    }
+    changed
+}
+
+/// Creates a [`Router`] redirecting every request to the same host and path over HTTPS on `https_port`.
+fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        redirect(&headers, &uri, https_port)
+    })
+}
+
+fn redirect(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok());
+    let Some(host) = host else {
+        debug!("Rejecting redirect of '{}' without a valid Host", uri);
+        return (StatusCode::BAD_REQUEST, "Missing or invalid Host header").into_response();
+    };
+
+    let path = uri.path_and_query().map_or("/", |pq| pq.as_str());
+    let location = match https_port {
+        443 => format!("https://{}{}", host.host(), path),
+        port => format!("https://{}:{}{}", host.host(), port, path),
+    };
+    trace!("Redirecting '{}' to '{}'", uri, location);
+    Redirect::permanent(&location).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Listen, Server};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn fixture(dir: &Path) -> Tls {
+        for name in ["cert.pem", "key.pem"] {
+            std::fs::copy(Path::new("test_files/tls").join(name), dir.join(name)).unwrap();
+        }
+        Tls {
+            cert: dir.join("cert.pem").display().to_string(),
+            key: dir.join("key.pem").display().to_string(),
+            redirect_port: None,
+        }
+    }
+
+    #[test]
+    fn redirect_locations() {
+        let location = |host: Option<&str>, uri: &str, port| {
+            let mut headers = HeaderMap::new();
+            if let Some(host) = host {
+                headers.insert(header::HOST, host.parse().unwrap());
+            }
+            let resp = redirect(&headers, &uri.parse().unwrap(), port);
+            let location = resp.headers().get(header::LOCATION).cloned();
+            (
+                resp.status(),
+                location.map(|l| l.to_str().unwrap().to_owned()),
+            )
+        };
+
+        assert_eq!(
+            location(Some("example.site:80"), "/one/posts?page=2", 443),
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                Some("https://example.site/one/posts?page=2".to_owned())
+            )
+        );
+        assert_eq!(
+            location(Some("[::1]:8080"), "/", 8443),
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                Some("https://[::1]:8443/".to_owned())
+            )
+        );
+        assert_eq!(location(None, "/", 443), (StatusCode::BAD_REQUEST, None));
+        assert_eq!(
+            location(Some("bad host"), "/", 443),
+            (StatusCode::BAD_REQUEST, None)
+        );
+    }
+
+    #[tokio::test]
+    async fn check_certificate_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let tls = fixture(dir.path());
+        let config = load(&tls).await.unwrap();
+        assert_eq!(
+            config.get_inner().alpn_protocols,
+            [b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+        let task = tokio::spawn(watch(config.clone(), tls.clone()));
+        tokio::time::sleep(DEBOUNCE).await;
+
+        let initial = config.get_inner();
+        std::fs::copy("test_files/tls/cert.pem", &tls.cert).unwrap();
+        let mut reloaded = false;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if !Arc::ptr_eq(&initial, &config.get_inner()) {
+                reloaded = true;
+                break;
+            }
+        }
+        assert!(reloaded);
+
+        let current = config.get_inner();
+        std::fs::write(&tls.cert, "not a certificate").unwrap();
+        tokio::time::sleep(DEBOUNCE * 5).await;
+        task.abort();
+        assert!(Arc::ptr_eq(&current, &config.get_inner()));
+    }
+
+    #[tokio::test]
+    async fn check_https_and_redirect() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tls = fixture(dir.path());
+        tls.redirect_port = Some(9094);
+        // The redirect targets the port actually listened on, rather than the unused `port`.
+        let server = Server {
+            listen: vec![Listen::Tcp("127.0.0.1:9093".parse().unwrap())],
+            tls: Some(tls.clone()),
+            ..Server::new()
+        };
+        let router = Router::new().route("/", axum::routing::get(|| async { "Secure" }));
+        let listeners = listen::bind(&server).unwrap();
+        let task = tokio::spawn(async move { serve(&tls, listeners, router).await });
+
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let mut resp = None;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if let Ok(ok) = client.get("https://localhost:9093/").send().await {
+                resp = Some(ok);
+                break;
+            }
+        }
+        let resp = resp.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "Secure");
+
+        let resp = client
+            .get("http://localhost:9094/one?page=2")
+            .send()
+            .await
+            .unwrap();
+        task.abort();
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers()[header::LOCATION],
+            "https://localhost:9093/one?page=2"
+        );
+    }
+}
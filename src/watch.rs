@@ -0,0 +1,380 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Provides filesystem and signal watching to keep a running site in step with its files on disk.
+//!
+//! - Changes under `docpaths.webroot` clear the current [`Engine`]'s render cache, after which
+//!   the search index is reloaded, except for changes to the saved search index itself.
+//! - Changes to the configuration file or under `docpaths.templates`, and `SIGHUP` on UNIX-like
+//!   systems, rebuild the [`Engine`] from disk and swap it into the [`SharedEngine`].
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{debug, error, info, trace, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use super::check;
+use super::config::AppConfig;
+use super::render::{Engine, SharedEngine, SEARCH_INDEX_FILE};
+use super::{Context, Result};
+
+/// Time allowed for a burst of related events, such as an editor saving a file, to settle.
+pub(crate) const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// What a filesystem event requires of the running site, in increasing order of effort.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Action {
+    Ignore,
+    Invalidate,
+    Reload,
+}
+
+/// Paths being watched, canonicalized so they can be compared with event paths.
+#[derive(Debug, Default, PartialEq)]
+struct Watched {
+    config: PathBuf,
+    templates: Option<PathBuf>,
+    webroot: Option<PathBuf>,
+}
+
+impl Watched {
+    fn new(config_path: &Path, app: &AppConfig) -> Result<Watched> {
+        let config = config_path
+            .canonicalize()
+            .with_context(|| format!("failed to resolve '{}'", config_path.display()))?;
+        Ok(Watched {
+            config,
+            templates: Path::new(&app.docpaths.templates).canonicalize().ok(),
+            webroot: Path::new(&app.docpaths.webroot).canonicalize().ok(),
+        })
+    }
+
+    /// Directories to watch, with how deeply to watch each.
+    fn roots(&self) -> Vec<(&Path, RecursiveMode)> {
+        let mut roots = Vec::new();
+        if let Some(parent) = self.config.parent() {
+            roots.push((parent, RecursiveMode::NonRecursive));
+        }
+        for dir in [&self.templates, &self.webroot].into_iter().flatten() {
+            roots.push((dir.as_path(), RecursiveMode::Recursive));
+        }
+        roots
+    }
+
+    /// Whether `path` is the saved search index, or the temporary file it is written through,
+    /// which change as a result of rebuilding the index rather than of the site's content.
+    fn is_search_index(&self, path: &Path) -> bool {
+        let saved = |name: &str| {
+            name == SEARCH_INDEX_FILE
+                || name.strip_prefix('.').and_then(|n| n.strip_suffix(".tmp"))
+                    == Some(SEARCH_INDEX_FILE)
+        };
+        path.parent() == self.webroot.as_deref()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(saved)
+    }
+
+    fn classify(&self, event: &Event) -> Action {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return Action::Ignore;
+        }
+
+        let within = |path: &Path, dir: &Option<PathBuf>| {
+            dir.as_ref().is_some_and(|dir| path.starts_with(dir))
+        };
+        event
+            .paths
+            .iter()
+            .map(|path| {
+                if path == &self.config || within(path, &self.templates) {
+                    Action::Reload
+                } else if self.is_search_index(path) {
+                    Action::Ignore
+                } else if within(path, &self.webroot) {
+                    Action::Invalidate
+                } else {
+                    Action::Ignore
+                }
+            })
+            .max()
+            .unwrap_or(Action::Ignore)
+    }
+
+    fn watch(&self, watcher: &mut RecommendedWatcher) -> Result<()> {
+        for (path, mode) in self.roots() {
+            info!("Watching '{}' for changes", path.display());
+            watcher
+                .watch(path, mode)
+                .with_context(|| format!("failed to watch '{}'", path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn unwatch(&self, watcher: &mut RecommendedWatcher) {
+        for (path, _) in self.roots() {
+            if let Err(err) = watcher.unwatch(path) {
+                debug!("Failed to stop watching '{}': {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// Watches the site's configuration, templates, and content until an unrecoverable error occurs.
+pub(crate) async fn watch(engine: SharedEngine, config_path: PathBuf) -> Result<()> {
+    let (tx, mut rx) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let _ = tx.send(res);
+    })
+    .context("failed to create filesystem watcher")?;
+
+    let mut watched = Watched::new(&config_path, &engine.load().app)?;
+    watched.watch(&mut watcher)?;
+
+    #[cfg(target_family = "unix")]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .context("failed to listen for SIGHUP")?;
+    #[cfg(not(target_family = "unix"))]
+    let mut hangup = ();
+
+    loop {
+        let action = tokio::select! {
+            res = rx.recv() => match res {
+                Some(res) => settle(&watched, res, &mut rx).await,
+                None => return Ok(()),
+            },
+            _ = sighup(&mut hangup) => {
+                info!("Received SIGHUP");
+                Action::Reload
+            }
+        };
+
+        match action {
+            Action::Ignore => {}
+            Action::Invalidate => {
+                debug!("Site content changed");
+                engine.load().cache.clear();
+            }
+            Action::Reload => match reload(&engine, &config_path) {
+                Ok(()) => rewatch(&mut watched, &mut watcher, &config_path, &engine.load().app),
+                Err(err) => error!(
+                    "Failed to reload configuration, continuing with the previous one: {:#}",
+                    err
+                ),
+            },
+        }
+
+        if action != Action::Ignore
+            && let Err(err) = engine.load().search_index().await
+        {
+            error!("Failed to rebuild search index: {:#}", err);
+        }
+    }
+}
+
+/// Moves `watcher` over to the paths of the reloaded `app`, keeping the current ones on failure.
+fn rewatch(
+    watched: &mut Watched,
+    watcher: &mut RecommendedWatcher,
+    config_path: &Path,
+    app: &AppConfig,
+) {
+    let next = match Watched::new(config_path, app) {
+        Ok(next) => next,
+        Err(err) => {
+            error!(
+                "Failed to resolve watched paths, continuing with the previous ones: {:#}",
+                err
+            );
+            return;
+        }
+    };
+    if next == *watched {
+        return;
+    }
+
+    watched.unwatch(watcher);
+    match next.watch(watcher) {
+        Ok(()) => *watched = next,
+        Err(err) => {
+            error!(
+                "Failed to watch new paths, continuing with the previous ones: {:#}",
+                err
+            );
+            next.unwatch(watcher);
+            if let Err(err) = watched.watch(watcher) {
+                error!("Failed to restore watched paths: {:#}", err);
+            }
+        }
+    }
+}
+
+/// Waits out a burst of events following `first`, returning the most significant [`Action`].
+async fn settle(
+    watched: &Watched,
+    first: notify::Result<Event>,
+    rx: &mut UnboundedReceiver<notify::Result<Event>>,
+) -> Action {
+    tokio::time::sleep(DEBOUNCE).await;
+    let mut action = Action::Ignore;
+    for res in std::iter::once(first).chain(std::iter::from_fn(|| rx.try_recv().ok())) {
+        match res {
+            Ok(event) => {
+                trace!("Filesystem event: {:?}", event);
+                action = action.max(watched.classify(&event));
+            }
+            Err(err) => error!("Filesystem watch error: {}", err),
+        }
+    }
+    action
+}
+
+/// Rebuilds the [`Engine`] from the configuration at `config_path`, replacing the current one.
+fn reload(engine: &SharedEngine, config_path: &Path) -> Result<()> {
+    info!("Reloading configuration from '{}'", config_path.display());
+    let app = check::load(config_path)?;
+    if app.server != engine.load().app.server {
+        warn!("Changes to the [server] configuration take effect after a restart");
+    }
+    if app.compression != engine.load().app.compression {
+        warn!("Changes to the [compression] configuration take effect after a restart");
+    }
+
+    let mut next = Engine::new(app)?;
+    next.drafts = engine.load().drafts;
+    engine.store(next);
+    info!("Configuration reloaded");
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+pub(crate) async fn sighup(hangup: &mut tokio::signal::unix::Signal) {
+    hangup.recv().await;
+}
+
+#[cfg(not(target_family = "unix"))]
+pub(crate) async fn sighup(_: &mut ()) {
+    std::future::pending::<()>().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::future::Future;
+
+    async fn eventually<F: Future<Output = bool>>(check: impl Fn() -> F) -> bool {
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if check().await {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn check_cache_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let engine = SharedEngine::new(Engine::new(config).unwrap());
+        let task = tokio::spawn(watch(engine.clone(), dir.path().join("config.toml")));
+
+        let page = engine.load().render_topic("one", 1).await.unwrap().unwrap();
+        assert!(page.contains("Coming Soon"));
+        tokio::time::sleep(DEBOUNCE).await;
+
+        std::fs::write(
+            dir.path().join("site/webroot/one/posts/1.md"),
+            "Fresh post\n",
+        )
+        .unwrap();
+
+        let refreshed = eventually(|| async {
+            let page = engine.load().render_topic("one", 1).await.unwrap().unwrap();
+            page.contains("Fresh post")
+        })
+        .await;
+        task.abort();
+        assert!(refreshed);
+    }
+
+    #[test]
+    fn saved_search_index_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = testing::site(&dir, &["One", "Gallery"]);
+        let watched = Watched::new(&dir.path().join("config.toml"), &app).unwrap();
+        let webroot = watched.webroot.clone().unwrap();
+        let event = |path: PathBuf| Event::new(EventKind::Any).add_path(path);
+
+        let tmp = format!(".{}.tmp", SEARCH_INDEX_FILE);
+        assert_eq!(
+            watched.classify(&event(webroot.join(SEARCH_INDEX_FILE))),
+            Action::Ignore
+        );
+        assert_eq!(watched.classify(&event(webroot.join(tmp))), Action::Ignore);
+        let post = webroot.join("one/posts").join(SEARCH_INDEX_FILE);
+        assert_eq!(watched.classify(&event(post)), Action::Invalidate);
+    }
+
+    #[tokio::test]
+    async fn check_config_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testing::site(&dir, &["One", "Gallery"]);
+        let config_path = dir.path().join("config.toml");
+        let engine = SharedEngine::new(Engine::new(config).unwrap());
+        let task = tokio::spawn(watch(engine.clone(), config_path.clone()));
+        tokio::time::sleep(DEBOUNCE).await;
+
+        let mut app = AppConfig::from_path(&config_path).unwrap();
+        app.site.topics.push("Two".to_owned());
+        for sub in ["posts", "ext"] {
+            std::fs::create_dir_all(dir.path().join("site/webroot/two").join(sub)).unwrap();
+        }
+        std::fs::write(&config_path, toml::to_string_pretty(&app).unwrap()).unwrap();
+
+        let reloaded =
+            eventually(|| async { engine.load().topic_slugs.contains(&"two".to_owned()) }).await;
+        assert!(reloaded);
+
+        let current = engine.load();
+        std::fs::write(&config_path, "[site]\nname = ").unwrap();
+        tokio::time::sleep(DEBOUNCE * 5).await;
+        task.abort();
+        assert!(std::sync::Arc::ptr_eq(&current, &engine.load()));
+    }
+
+    #[test]
+    fn rewatch_keeps_previous_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = testing::site(&dir, &["One", "Gallery"]);
+        let config_path = dir.path().join("config.toml");
+        let mut watcher = notify::recommended_watcher(|_: notify::Result<Event>| {}).unwrap();
+        let mut watched = Watched::new(&config_path, &app).unwrap();
+        watched.watch(&mut watcher).unwrap();
+
+        let previous = Watched::new(&config_path, &app).unwrap();
+        rewatch(
+            &mut watched,
+            &mut watcher,
+            &dir.path().join("missing.toml"),
+            &app,
+        );
+        assert_eq!(watched, previous);
+
+        let mut app = app;
+        app.docpaths.templates = dir.path().display().to_string();
+        rewatch(&mut watched, &mut watcher, &config_path, &app);
+        assert_eq!(watched.templates, Some(dir.path().canonicalize().unwrap()));
+    }
+}